@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::rc::Rc;
+
 use crate::{Radix, Span};
 
 #[derive(Clone, Debug)]
@@ -16,6 +19,38 @@ impl<'a> Stmt<'a> {
 pub enum StmtKind<'a> {
     Expr(Expr<'a>),
     Import(Ident<'a>),
+    Let(Box<Let<'a>>),
+    For(Box<For<'a>>),
+    While(Box<While<'a>>),
+    Block(Block<'a>),
+    Break,
+    Continue,
+}
+
+#[derive(Clone, Debug)]
+pub struct Let<'a> {
+    pub bind: Pattern<'a>,
+    pub value: Expr<'a>,
+}
+
+#[derive(Clone, Debug)]
+pub struct For<'a> {
+    pub bind: Pattern<'a>,
+    pub source: Expr<'a>,
+    pub body: Block<'a>,
+}
+
+#[derive(Clone, Debug)]
+pub struct While<'a> {
+    pub cond: Expr<'a>,
+    pub body: Block<'a>,
+}
+
+/// Statements in braces, which are executed in a new scope.
+#[derive(Clone, Debug)]
+pub struct Block<'a> {
+    pub span: Span,
+    pub stmts: Vec<Stmt<'a>>,
 }
 
 #[derive(Clone, Debug)]
@@ -34,9 +69,14 @@ impl<'a> Expr<'a> {
     }
 
     pub(crate) fn name(span: Span, name: &'a str) -> Self {
+        let name = name.into();
         Self::new(span.clone(), ExprKind::Name(Ident { span, name }))
     }
 
+    pub(crate) fn format(span: Span, parts: Vec<FormatPart<'a>>) -> Self {
+        Self::new(span, ExprKind::Format(parts))
+    }
+
     pub(crate) fn list(span: Span, list: Vec<Expr<'a>>) -> Self {
         Self::new(span, ExprKind::List(list))
     }
@@ -58,6 +98,15 @@ impl<'a> Expr<'a> {
         Self::new(span, ExprKind::Field(expr.into(), field))
     }
 
+    pub(crate) fn safe_field(expr: Expr<'a>, field: Field<'a>) -> Self {
+        let span = expr.span.start..field.span.end;
+        Self::new(span, ExprKind::SafeField(expr.into(), field))
+    }
+
+    pub(crate) fn range(span: Span, range: Range<'a>) -> Self {
+        Self::new(span, ExprKind::Range(range.into()))
+    }
+
     pub(crate) fn unop(op: Spanned<UnOp>, expr: Expr<'a>) -> Self {
         let span = op.span.start..expr.span.end;
         Self::new(span, ExprKind::UnOp(op, expr.into()))
@@ -78,6 +127,11 @@ impl<'a> Expr<'a> {
         Self::new(span, ExprKind::BoolOp(op, lhs.into(), rhs.into()))
     }
 
+    pub(crate) fn coalesce(lhs: Expr<'a>, rhs: Expr<'a>) -> Self {
+        let span = lhs.span.start..rhs.span.end;
+        Self::new(span, ExprKind::Coalesce(lhs.into(), rhs.into()))
+    }
+
     pub(crate) fn insert(span: Span, insert: Insert<'a>) -> Self {
         Self::new(span, ExprKind::Insert(insert.into()))
     }
@@ -94,6 +148,23 @@ impl<'a> Expr<'a> {
         Self::new(span, ExprKind::Select(select.into()))
     }
 
+    pub(crate) fn setop(op: Spanned<SetOp>, lhs: Expr<'a>, rhs: Expr<'a>) -> Self {
+        let span = lhs.span.start..rhs.span.end;
+        Self::new(span, ExprKind::SetOp(op, lhs.into(), rhs.into()))
+    }
+
+    pub(crate) fn block(block: Block<'a>) -> Self {
+        Self::new(block.span.clone(), ExprKind::Block(block))
+    }
+
+    pub(crate) fn if_(span: Span, if_: If<'a>) -> Self {
+        Self::new(span, ExprKind::If(if_.into()))
+    }
+
+    pub(crate) fn func(span: Span, func: FuncDef) -> Self {
+        Self::new(span, ExprKind::Func(func.into()))
+    }
+
     pub(crate) fn assign(lhs: Expr<'a>, rhs: Expr<'a>) -> Self {
         let span = lhs.span.start..rhs.span.end;
         Self::new(span, ExprKind::Assign(lhs.into(), rhs.into()))
@@ -109,19 +180,28 @@ impl<'a> Expr<'a> {
 pub enum ExprKind<'a> {
     Lit(Lit<'a>),
     Name(Ident<'a>),
+    Format(Vec<FormatPart<'a>>),
     List(Vec<Expr<'a>>),
     Hash(Vec<(Field<'a>, Expr<'a>)>),
     Call(Box<Expr<'a>>, Vec<Expr<'a>>),
     Index(Box<Expr<'a>>, Box<Expr<'a>>),
     Field(Box<Expr<'a>>, Field<'a>),
+    // A field access that is null if the object is null or has no such field
+    SafeField(Box<Expr<'a>>, Field<'a>),
+    Range(Box<Range<'a>>),
     UnOp(Spanned<UnOp>, Box<Expr<'a>>),
     BinOp(Spanned<BinOp>, Box<Expr<'a>>, Box<Expr<'a>>),
     CmpOp(Spanned<CmpOp>, Box<Expr<'a>>, Box<Expr<'a>>),
     BoolOp(Spanned<BoolOp>, Box<Expr<'a>>, Box<Expr<'a>>),
+    Coalesce(Box<Expr<'a>>, Box<Expr<'a>>),
     Insert(Box<Insert<'a>>),
     Update(Box<Update<'a>>),
     Delete(Box<Delete<'a>>),
     Select(Box<Select<'a>>),
+    SetOp(Spanned<SetOp>, Box<Expr<'a>>, Box<Expr<'a>>),
+    Block(Block<'a>),
+    If(Box<If<'a>>),
+    Func(Rc<FuncDef>),
     Assign(Box<Expr<'a>>, Box<Expr<'a>>),
     CompoundAssign(Spanned<BinOp>, Box<Expr<'a>>, Box<Expr<'a>>),
 }
@@ -136,21 +216,27 @@ pub struct Lit<'a> {
 pub enum LitKind<'a> {
     Null,
     Bool(bool),
-    Str(&'a str),
-    Int(&'a str, Radix),
-    Float(&'a str),
+    Str(Cow<'a, str>),
+    Int(Cow<'a, str>, Radix),
+    Float(Cow<'a, str>),
+}
+
+#[derive(Clone, Debug)]
+pub enum FormatPart<'a> {
+    Str(Cow<'a, str>),
+    Expr(Expr<'a>),
 }
 
 #[derive(Clone, Debug)]
 pub struct Ident<'a> {
     pub span: Span,
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Field<'a> {
     pub span: Span,
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
 }
 
 #[derive(Clone, Debug)]
@@ -165,6 +251,13 @@ impl<T> Spanned<T> {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct Range<'a> {
+    pub start: Option<Expr<'a>>,
+    pub end: Option<Expr<'a>>,
+    pub inclusive: bool,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UnOp {
     Not,
@@ -203,43 +296,519 @@ pub enum BoolOp {
     And,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetOp {
+    Union,
+    Intersect,
+    Except,
+}
+
 #[derive(Clone, Debug)]
 pub struct Insert<'a> {
     pub into: Expr<'a>,
     pub values: Vec<Expr<'a>>,
+    // The pattern that binds each inserted item, and the returned expression
+    pub returning: Option<(Pattern<'a>, Expr<'a>)>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Update<'a> {
     pub from: FromClause<'a>,
     pub exprs: Vec<Expr<'a>>,
+    pub returning: Option<Expr<'a>>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Delete<'a> {
     pub from: FromClause<'a>,
-    pub target: Ident<'a>,
+    pub target: Expr<'a>,
+    pub returning: Option<Expr<'a>>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Select<'a> {
     pub from: FromClause<'a>,
+    pub group_by: Option<Expr<'a>>,
+    pub order_by: Vec<SortKey<'a>>,
+    pub distinct: bool,
     pub project: Option<Expr<'a>>,
+    pub limit: Option<Expr<'a>>,
+    pub offset: Option<Expr<'a>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SortKey<'a> {
+    pub expr: Expr<'a>,
+    pub desc: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct If<'a> {
+    pub cond: Expr<'a>,
+    pub then: Expr<'a>,
+    pub else_: Option<Expr<'a>>,
+}
+
+/// A function definition.
+#[derive(Clone, Debug)]
+pub struct Func<'a> {
+    pub span: Span,
+    pub name: Option<Ident<'a>>,
+    pub params: Vec<Pattern<'a>>,
+    pub body: Expr<'a>,
+}
+
+/// A function definition with the input it's parsed from.
+///
+/// Functions can be called after their input is gone, so they own their
+/// syntax tree. The input is shared by the functions parsed from it, for the
+/// spans of errors in their bodies.
+#[derive(Debug)]
+pub struct FuncDef {
+    func: Func<'static>,
+    source: Rc<str>,
+}
+
+impl FuncDef {
+    pub(crate) fn new(func: Func<'_>, source: Rc<str>) -> Self {
+        Self {
+            func: func.into_owned(),
+            source,
+        }
+    }
+
+    pub fn func(&self) -> &Func<'static> {
+        &self.func
+    }
+
+    /// Returns the input that the spans of the function refer to.
+    pub fn source(&self) -> &Rc<str> {
+        &self.source
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Pattern<'a> {
+    pub span: Span,
+    pub kind: PatternKind<'a>,
+}
+
+impl<'a> Pattern<'a> {
+    pub(crate) fn new(span: Span, kind: PatternKind<'a>) -> Self {
+        Self { span, kind }
+    }
+
+    pub(crate) fn name(ident: Ident<'a>) -> Self {
+        Self::new(ident.span.clone(), PatternKind::Name(ident))
+    }
+
+    /// Returns the names bound by the pattern.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names<'b>(&'b self, names: &mut Vec<&'b str>) {
+        match &self.kind {
+            PatternKind::Name(ident) => names.push(&ident.name),
+            PatternKind::Pair(key, value) => {
+                key.collect_names(names);
+                value.collect_names(names);
+            }
+            PatternKind::Hash(fields) => {
+                for (_, pattern) in fields {
+                    pattern.collect_names(names);
+                }
+            }
+            PatternKind::List(items) => {
+                for pattern in items {
+                    pattern.collect_names(names);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum PatternKind<'a> {
+    Name(Ident<'a>),
+    // The key and the value of a hash entry
+    Pair(Box<Pattern<'a>>, Box<Pattern<'a>>),
+    Hash(Vec<(Field<'a>, Pattern<'a>)>),
+    List(Vec<Pattern<'a>>),
 }
 
 #[derive(Clone, Debug)]
 pub struct FromClause<'a> {
     pub span: Span,
-    pub bind: Ident<'a>,
+    pub bind: Pattern<'a>,
     pub source: Expr<'a>,
-    pub join: Option<JoinClause<'a>>,
+    pub joins: Vec<JoinClause<'a>>,
     pub filter: Option<Expr<'a>>,
 }
 
 #[derive(Clone, Debug)]
 pub struct JoinClause<'a> {
     pub span: Span,
-    pub bind: Ident<'a>,
+    pub kind: JoinKind,
+    pub bind: Pattern<'a>,
     pub source: Expr<'a>,
     pub filter: Option<Expr<'a>>,
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Full,
+    // A source after a comma in a from clause, which is evaluated for each
+    // row and can refer to the names bound before it
+    Lateral,
+}
+
+/// Converts a syntax tree that borrows the input into one that owns it.
+pub(crate) trait IntoOwned {
+    type Owned;
+
+    fn into_owned(self) -> Self::Owned;
+}
+
+impl<T: IntoOwned> IntoOwned for Box<T> {
+    type Owned = Box<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        Box::new((*self).into_owned())
+    }
+}
+
+impl<T: IntoOwned> IntoOwned for Vec<T> {
+    type Owned = Vec<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        self.into_iter().map(T::into_owned).collect()
+    }
+}
+
+impl<T: IntoOwned> IntoOwned for Option<T> {
+    type Owned = Option<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        self.map(T::into_owned)
+    }
+}
+
+impl<T: IntoOwned, U: IntoOwned> IntoOwned for (T, U) {
+    type Owned = (T::Owned, U::Owned);
+
+    fn into_owned(self) -> Self::Owned {
+        (self.0.into_owned(), self.1.into_owned())
+    }
+}
+
+fn owned(s: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(s.into_owned())
+}
+
+// Functions already own their syntax tree.
+impl IntoOwned for Rc<FuncDef> {
+    type Owned = Self;
+
+    fn into_owned(self) -> Self::Owned {
+        self
+    }
+}
+
+impl<'a> IntoOwned for Stmt<'a> {
+    type Owned = Stmt<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        let kind = match self.kind {
+            StmtKind::Expr(expr) => StmtKind::Expr(expr.into_owned()),
+            StmtKind::Import(ident) => StmtKind::Import(ident.into_owned()),
+            StmtKind::Let(let_) => StmtKind::Let(let_.into_owned()),
+            StmtKind::For(for_) => StmtKind::For(for_.into_owned()),
+            StmtKind::While(while_) => StmtKind::While(while_.into_owned()),
+            StmtKind::Block(block) => StmtKind::Block(block.into_owned()),
+            StmtKind::Break => StmtKind::Break,
+            StmtKind::Continue => StmtKind::Continue,
+        };
+        Stmt::new(self.span, kind)
+    }
+}
+
+impl<'a> IntoOwned for Let<'a> {
+    type Owned = Let<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Let {
+            bind: self.bind.into_owned(),
+            value: self.value.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for For<'a> {
+    type Owned = For<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        For {
+            bind: self.bind.into_owned(),
+            source: self.source.into_owned(),
+            body: self.body.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for While<'a> {
+    type Owned = While<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        While {
+            cond: self.cond.into_owned(),
+            body: self.body.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Block<'a> {
+    type Owned = Block<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Block {
+            span: self.span,
+            stmts: self.stmts.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Expr<'a> {
+    type Owned = Expr<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        use ExprKind::*;
+        let kind = match self.kind {
+            Lit(lit) => Lit(lit.into_owned()),
+            Name(ident) => Name(ident.into_owned()),
+            Format(parts) => Format(parts.into_owned()),
+            List(list) => List(list.into_owned()),
+            Hash(hash) => Hash(hash.into_owned()),
+            Call(expr, args) => Call(expr.into_owned(), args.into_owned()),
+            Index(expr, index) => Index(expr.into_owned(), index.into_owned()),
+            Field(expr, field) => Field(expr.into_owned(), field.into_owned()),
+            SafeField(expr, field) => SafeField(expr.into_owned(), field.into_owned()),
+            Range(range) => Range(range.into_owned()),
+            UnOp(op, expr) => UnOp(op, expr.into_owned()),
+            BinOp(op, lhs, rhs) => BinOp(op, lhs.into_owned(), rhs.into_owned()),
+            CmpOp(op, lhs, rhs) => CmpOp(op, lhs.into_owned(), rhs.into_owned()),
+            BoolOp(op, lhs, rhs) => BoolOp(op, lhs.into_owned(), rhs.into_owned()),
+            Coalesce(lhs, rhs) => Coalesce(lhs.into_owned(), rhs.into_owned()),
+            Insert(insert) => Insert(insert.into_owned()),
+            Update(update) => Update(update.into_owned()),
+            Delete(delete) => Delete(delete.into_owned()),
+            Select(select) => Select(select.into_owned()),
+            SetOp(op, lhs, rhs) => SetOp(op, lhs.into_owned(), rhs.into_owned()),
+            Block(block) => Block(block.into_owned()),
+            If(if_) => If(if_.into_owned()),
+            Func(func) => Func(func),
+            Assign(lhs, rhs) => Assign(lhs.into_owned(), rhs.into_owned()),
+            CompoundAssign(op, lhs, rhs) => CompoundAssign(op, lhs.into_owned(), rhs.into_owned()),
+        };
+        Expr::new(self.span, kind)
+    }
+}
+
+impl<'a> IntoOwned for Lit<'a> {
+    type Owned = Lit<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        let kind = match self.kind {
+            LitKind::Null => LitKind::Null,
+            LitKind::Bool(b) => LitKind::Bool(b),
+            LitKind::Str(s) => LitKind::Str(owned(s)),
+            LitKind::Int(s, radix) => LitKind::Int(owned(s), radix),
+            LitKind::Float(s) => LitKind::Float(owned(s)),
+        };
+        Lit {
+            span: self.span,
+            kind,
+        }
+    }
+}
+
+impl<'a> IntoOwned for FormatPart<'a> {
+    type Owned = FormatPart<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            FormatPart::Str(s) => FormatPart::Str(owned(s)),
+            FormatPart::Expr(expr) => FormatPart::Expr(expr.into_owned()),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Ident<'a> {
+    type Owned = Ident<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Ident {
+            span: self.span,
+            name: owned(self.name),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Field<'a> {
+    type Owned = Field<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Field {
+            span: self.span,
+            name: owned(self.name),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Range<'a> {
+    type Owned = Range<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Range {
+            start: self.start.into_owned(),
+            end: self.end.into_owned(),
+            inclusive: self.inclusive,
+        }
+    }
+}
+
+impl<'a> IntoOwned for Insert<'a> {
+    type Owned = Insert<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Insert {
+            into: self.into.into_owned(),
+            values: self.values.into_owned(),
+            returning: self.returning.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Update<'a> {
+    type Owned = Update<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Update {
+            from: self.from.into_owned(),
+            exprs: self.exprs.into_owned(),
+            returning: self.returning.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Delete<'a> {
+    type Owned = Delete<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Delete {
+            from: self.from.into_owned(),
+            target: self.target.into_owned(),
+            returning: self.returning.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Select<'a> {
+    type Owned = Select<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Select {
+            from: self.from.into_owned(),
+            group_by: self.group_by.into_owned(),
+            order_by: self.order_by.into_owned(),
+            distinct: self.distinct,
+            project: self.project.into_owned(),
+            limit: self.limit.into_owned(),
+            offset: self.offset.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for SortKey<'a> {
+    type Owned = SortKey<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        SortKey {
+            expr: self.expr.into_owned(),
+            desc: self.desc,
+        }
+    }
+}
+
+impl<'a> IntoOwned for If<'a> {
+    type Owned = If<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        If {
+            cond: self.cond.into_owned(),
+            then: self.then.into_owned(),
+            else_: self.else_.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Func<'a> {
+    type Owned = Func<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Func {
+            span: self.span,
+            name: self.name.into_owned(),
+            params: self.params.into_owned(),
+            body: self.body.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for Pattern<'a> {
+    type Owned = Pattern<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        let kind = match self.kind {
+            PatternKind::Name(ident) => PatternKind::Name(ident.into_owned()),
+            PatternKind::Pair(key, value) => {
+                PatternKind::Pair(key.into_owned(), value.into_owned())
+            }
+            PatternKind::Hash(fields) => PatternKind::Hash(fields.into_owned()),
+            PatternKind::List(items) => PatternKind::List(items.into_owned()),
+        };
+        Pattern::new(self.span, kind)
+    }
+}
+
+impl<'a> IntoOwned for FromClause<'a> {
+    type Owned = FromClause<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        FromClause {
+            span: self.span,
+            bind: self.bind.into_owned(),
+            source: self.source.into_owned(),
+            joins: self.joins.into_owned(),
+            filter: self.filter.into_owned(),
+        }
+    }
+}
+
+impl<'a> IntoOwned for JoinClause<'a> {
+    type Owned = JoinClause<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        JoinClause {
+            span: self.span,
+            kind: self.kind,
+            bind: self.bind.into_owned(),
+            source: self.source.into_owned(),
+            filter: self.filter.into_owned(),
+        }
+    }
+}
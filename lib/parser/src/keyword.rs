@@ -13,6 +13,10 @@ macro_rules! keywords {
 
 keywords!(
     IN => "in",
+    FN => "fn",
+    IF => "if",
+    FOR => "for",
+    LET => "let",
     ON => "on",
     NOT => "not",
     NULL => "null",
@@ -20,12 +24,33 @@ keywords!(
     FALSE => "false",
     INTO => "into",
     FROM => "from",
+    ELSE => "else",
     JOIN => "join",
     WHERE => "where",
+    WHILE => "while",
+    BREAK => "break",
     LIMIT => "limit",
+    OFFSET => "offset",
     INSERT => "insert",
     UPDATE => "update",
+    EXCEPT => "except",
     DELETE => "delete",
     SELECT => "select",
+    UNION => "union",
+    DISTINCT => "distinct",
+    RETURNING => "returning",
+    INTERSECT => "intersect",
     IMPORT => "import",
+    CONTINUE => "continue",
 );
+
+// Contextual keywords, which are only keywords where their clause is
+// expected, and are names elsewhere.
+pub(crate) const BY: &str = "by";
+pub(crate) const ASC: &str = "asc";
+pub(crate) const THEN: &str = "then";
+pub(crate) const DESC: &str = "desc";
+pub(crate) const FULL: &str = "full";
+pub(crate) const LEFT: &str = "left";
+pub(crate) const GROUP: &str = "group";
+pub(crate) const ORDER: &str = "order";
@@ -1,50 +1,96 @@
+use std::borrow::Cow;
 use std::str::CharIndices;
 
 use crate::token::*;
 use crate::{Error, Radix, Result, Span};
 
+#[derive(Clone)]
 pub(crate) struct Lexer<'a> {
     input: &'a str,
     chars: CharIndices<'a>,
+    offset: usize,
     saved: Option<(usize, char)>,
 }
 
 impl<'a> Lexer<'a> {
     pub(crate) fn new(input: &'a str) -> Self {
+        Self::with_span(input, 0..input.len())
+    }
+
+    // Creates a lexer that only scans `span` of the input. Token spans are
+    // still relative to the whole input.
+    fn with_span(input: &'a str, span: Span) -> Self {
         Self {
-            input,
-            chars: input.char_indices(),
+            input: &input[..span.end],
+            chars: input[span.clone()].char_indices(),
+            offset: span.start,
             saved: None,
         }
     }
 
+    pub(crate) fn fork(&self, span: Span) -> Self {
+        Self::with_span(self.input, span)
+    }
+
     pub(crate) fn next(&mut self) -> Result<(Span, Token<'a>)> {
-        match self.skip_while(|c| c.is_whitespace()) {
-            Some((i, c)) => match c {
-                '"' => self.parse_str(i),
-                c if is_dec_digit(c) => {
-                    let num = self.parse_num(i, c)?;
-                    self.check_num_suffix().map(|_| num)
+        loop {
+            return match self.skip_while(|c| c.is_whitespace()) {
+                Some((_, '#')) => {
+                    self.skip_line_comment();
+                    continue;
                 }
-                c if is_ident_start(c) => self.parse_ident(i),
-                _ => self.parse_punct(i, c),
-            },
-            None => {
-                let len = self.input.len();
-                Ok((len..len, Token::End))
-            }
+                Some((i, '/')) => match self.take_if(|c| c == '/' || c == '*') {
+                    Some((_, '/')) => {
+                        self.skip_line_comment();
+                        continue;
+                    }
+                    Some(_) => {
+                        self.skip_block_comment(i)?;
+                        continue;
+                    }
+                    None => self.parse_punct(i, '/'),
+                },
+                Some((i, c)) => match c {
+                    '"' => self.parse_str(i, i, false),
+                    'r' if self.take_if(|c| c == '"').is_some() => self.parse_str(i, i + 1, true),
+                    'f' if self.take_if(|c| c == '"').is_some() => self.parse_fstr(i),
+                    c if is_dec_digit(c) => {
+                        let num = self.parse_num(i, c)?;
+                        self.check_num_suffix().map(|_| num)
+                    }
+                    c if is_ident_start(c) => self.parse_ident(i),
+                    _ => self.parse_punct(i, c),
+                },
+                None => {
+                    let len = self.input.len();
+                    Ok((len..len, Token::End))
+                }
+            };
         }
     }
 
     fn take(&mut self) -> Option<(usize, char)> {
-        self.saved.take().or_else(|| self.chars.next())
+        self.saved
+            .take()
+            .or_else(|| self.chars.next().map(|(i, c)| (i + self.offset, c)))
+    }
+
+    fn peek(&self) -> Option<char> {
+        match self.saved {
+            Some((_, c)) => Some(c),
+            None => self.chars.clone().next().map(|(_, c)| c),
+        }
     }
 
     fn save(&mut self, i: usize, c: char) {
         self.saved = Some((i, c));
     }
 
-    fn slice(&self, span: Span) -> &'a str {
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    pub(crate) fn slice(&self, span: Span) -> &'a str {
         // SAFETY: `span` is always valid for internal use.
         unsafe { self.input.get_unchecked(span) }
     }
@@ -69,22 +115,217 @@ impl<'a> Lexer<'a> {
         None
     }
 
-    fn parse_str(&mut self, start: usize) -> Result<(Span, Token<'a>)> {
+    fn skip_line_comment(&mut self) {
+        self.skip_while(|c| c != '\n');
+    }
+
+    fn skip_block_comment(&mut self, start: usize) -> Result<()> {
+        let mut depth = 1;
+        while let Some((_, c)) = self.take() {
+            match c {
+                '/' if self.take_if(|c| c == '*').is_some() => depth += 1,
+                '*' if self.take_if(|c| c == '/').is_some() => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(Error::incomplete(
+            start..self.input.len(),
+            "unterminated block comment",
+        ))
+    }
+
+    // Parses a string literal whose opening quote is at `quote`.
+    //
+    // `start` is the start of the literal, including the `r` prefix of raw
+    // strings. Raw strings keep backslashes as they are.
+    fn parse_str(&mut self, start: usize, quote: usize, raw: bool) -> Result<(Span, Token<'a>)> {
+        if self.take_if(|c| c == '"').is_some() {
+            if self.take_if(|c| c == '"').is_some() {
+                return self.parse_str_body(start, quote + 3, raw, true);
+            }
+            return Ok((start..quote + 2, Token::Str(Cow::Borrowed(""))));
+        }
+        self.parse_str_body(start, quote + 1, raw, false)
+    }
+
+    fn parse_str_body(
+        &mut self,
+        start: usize,
+        body: usize,
+        raw: bool,
+        triple: bool,
+    ) -> Result<(Span, Token<'a>)> {
+        // The decoded string is only allocated after the first escape.
+        let mut value: Option<String> = None;
+        let mut last = body;
         while let Some((i, c)) = self.take() {
             match c {
-                '"' => return Ok((start..i + 1, Token::Str(self.slice(start + 1..i)))),
-                '\\' => {
-                    let _ = self.take();
+                '"' if !triple => {
+                    let s = self.finish_str(value, last..i);
+                    return Ok((start..i + 1, Token::Str(s)));
+                }
+                '"' if self.take_if(|c| c == '"').is_some()
+                    && self.take_if(|c| c == '"').is_some() =>
+                {
+                    let s = self.finish_str(value, last..i);
+                    return Ok((start..i + 3, Token::Str(s)));
+                }
+                '\\' if !raw => {
+                    let (c, end) = self.parse_escape(i)?;
+                    let s = value.get_or_insert_with(String::new);
+                    s.push_str(self.slice(last..i));
+                    s.push(c);
+                    last = end;
                 }
                 _ => {}
             }
         }
+        let span = start..self.input.len();
+        if triple {
+            Err(Error::incomplete(span, "unterminated string literal"))
+        } else {
+            Err(Error::invalid_token(span, "unterminated string literal"))
+        }
+    }
+
+    fn parse_fstr(&mut self, start: usize) -> Result<(Span, Token<'a>)> {
+        let mut parts = Vec::new();
+        let mut value = String::new();
+        while let Some((i, c)) = self.take() {
+            match c {
+                '"' => {
+                    if !value.is_empty() {
+                        parts.push(FStrPart::Str(value.into()));
+                    }
+                    return Ok((start..i + 1, Token::FStr(parts)));
+                }
+                '\\' => value.push(self.parse_escape(i)?.0),
+                '{' if self.take_if(|c| c == '{').is_some() => value.push('{'),
+                '}' if self.take_if(|c| c == '}').is_some() => value.push('}'),
+                '{' => {
+                    if !value.is_empty() {
+                        parts.push(FStrPart::Str(std::mem::take(&mut value).into()));
+                    }
+                    parts.push(FStrPart::Expr(self.skip_fstr_expr(i)?));
+                }
+                '}' => {
+                    return Err(Error::invalid_token(
+                        i..i + 1,
+                        "single '}' in f-string, use '}}' instead",
+                    ))
+                }
+                _ => value.push(c),
+            }
+        }
         Err(Error::invalid_token(
             start..self.input.len(),
             "unterminated string literal",
         ))
     }
 
+    // Skips the embedded expression after the '{' at `start` and returns the
+    // span of the expression. The parser lexes the span again to parse it.
+    fn skip_fstr_expr(&mut self, start: usize) -> Result<Span> {
+        let mut depth = 0;
+        loop {
+            let (span, token) = self.next()?;
+            match token {
+                Token::Punct(Punct::LBrace) => depth += 1,
+                Token::Punct(Punct::RBrace) if depth == 0 => return Ok(start + 1..span.start),
+                Token::Punct(Punct::RBrace) => depth -= 1,
+                Token::End => {
+                    return Err(Error::invalid_token(
+                        start..span.end,
+                        "unterminated f-string expression",
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn finish_str(&self, value: Option<String>, rest: Span) -> Cow<'a, str> {
+        match value {
+            Some(mut s) => {
+                s.push_str(self.slice(rest));
+                Cow::Owned(s)
+            }
+            None => Cow::Borrowed(self.slice(rest)),
+        }
+    }
+
+    // Parses an escape sequence after the backslash at `start`.
+    //
+    // Returns the decoded character and the end of the escape sequence.
+    fn parse_escape(&mut self, start: usize) -> Result<(char, usize)> {
+        let (i, c) = match self.take() {
+            Some(x) => x,
+            None => {
+                return Err(Error::invalid_token(
+                    start..start + 1,
+                    "unterminated escape sequence",
+                ))
+            }
+        };
+        let c = match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'u' => return self.parse_unicode_escape(start),
+            _ => {
+                return Err(Error::invalid_token(
+                    start..i + c.len_utf8(),
+                    format!("invalid escape sequence '\\{c}'"),
+                ))
+            }
+        };
+        Ok((c, i + 1))
+    }
+
+    fn parse_unicode_escape(&mut self, start: usize) -> Result<(char, usize)> {
+        let error = |end: usize| {
+            Error::invalid_token(
+                start..end,
+                "invalid unicode escape, expect '\\u{...}' with 1 to 6 hex digits",
+            )
+        };
+        let (brace, _) = self.take_if(|c| c == '{').ok_or_else(|| error(start + 2))?;
+        let end = loop {
+            match self.take() {
+                Some((i, '}')) => break i,
+                Some((_, c)) if is_hex_digit(c) => {}
+                Some((i, c)) => {
+                    self.save(i, c);
+                    return Err(error(i));
+                }
+                None => return Err(error(self.input.len())),
+            }
+        };
+        let digits = self.slice(brace + 1..end);
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(error(end + 1));
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .map(|c| (c, end + 1))
+            .ok_or_else(|| {
+                Error::invalid_token(
+                    start..end + 1,
+                    format!("invalid unicode character '{digits}'"),
+                )
+            })
+    }
+
     fn parse_num(&mut self, start: usize, first: char) -> Result<(Span, Token<'a>)> {
         if first == '0' {
             if let Some((i, c)) = self.take() {
@@ -101,6 +342,11 @@ impl<'a> Lexer<'a> {
 
         let end = self.parse_digits(is_dec_digit)?;
         match self.take_if(|c| c == '.') {
+            // A '..' after an integer is a range, not a decimal point.
+            Some((i, c)) if self.peek() == Some('.') => {
+                self.save(i, c);
+                Ok((start..end, Token::Int(self.slice(start..end), Radix::Dec)))
+            }
             Some((i, _)) => {
                 let end = match self.parse_decimal()? {
                     Some(end) => self.parse_exponent()?.unwrap_or(end),
@@ -199,14 +445,20 @@ impl<'a> Lexer<'a> {
             ';' => (Semi, 1),
             ':' => (Colon, 1),
             ',' => (Comma, 1),
-            '.' => self.parse_punct_1(Dot, '.', DotDot),
+            '.' => match self.take_if(|c| c == '.') {
+                Some(_) => {
+                    let (punct, count) = self.parse_punct_1(DotDot, '=', DotDotEq);
+                    (punct, count + 1)
+                }
+                None => (Dot, 1),
+            },
             '(' => (LParen, 1),
             ')' => (RParen, 1),
             '{' => (LBrace, 1),
             '}' => (RBrace, 1),
             '[' => (LBracket, 1),
             ']' => (RBracket, 1),
-            '=' => self.parse_punct_1(Eq, '=', EqEq),
+            '=' => self.parse_punct_2(Eq, '=', EqEq, '>', FatArrow),
             '!' => self.parse_punct_1(Not, '=', NotEq),
             '+' => self.parse_punct_1(Plus, '=', PlusEq),
             '-' => self.parse_punct_1(Minus, '=', MinusEq),
@@ -218,6 +470,16 @@ impl<'a> Lexer<'a> {
             '&' => self.parse_punct_2(And, '=', AndEq, '&', AndAnd),
             '<' => self.parse_punct_3(LAngle, '=', LAngleEq, '<', LShift, '=', LShiftEq),
             '>' => self.parse_punct_3(RAngle, '=', RAngleEq, '>', RShift, '=', RShiftEq),
+            '?' => match self.take() {
+                Some((_, '.')) => (QuestionDot, 2),
+                Some((_, '?')) => (QuestionQuestion, 2),
+                _ => {
+                    return Err(Error::invalid_token(
+                        start..start + 1,
+                        "expected '?.' or '??'",
+                    ))
+                }
+            },
             _ => return Err(Error::invalid_token(start..start + 1, "")),
         };
         Ok((start..start + count, Token::Punct(punct)))
@@ -253,6 +515,7 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn parse_punct_3(
         &mut self,
         default: Punct,
@@ -283,7 +546,7 @@ fn is_bin_digit(c: char) -> bool {
 }
 
 fn is_oct_digit(c: char) -> bool {
-    c >= '0' && c <= '7'
+    ('0'..='7').contains(&c)
 }
 
 fn is_dec_digit(c: char) -> bool {
@@ -1,8 +1,10 @@
+use std::rc::Rc;
+
 pub mod ast;
 use ast::*;
 
 mod error;
-pub use error::{Error, Result};
+pub use error::{Error, ErrorKind, Result};
 
 mod lexer;
 use lexer::Lexer;
@@ -24,9 +26,14 @@ pub enum Radix {
     Hex = 16,
 }
 
+#[derive(Clone)]
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     saved: Option<(Span, Token<'a>)>,
+    // The copy of the input that functions are parsed from
+    source: Option<Rc<str>>,
+    // The number of loops around the statements being parsed
+    loops: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -34,6 +41,8 @@ impl<'a> Parser<'a> {
         Self {
             lexer: Lexer::new(input),
             saved: None,
+            source: None,
+            loops: 0,
         }
     }
 
@@ -48,6 +57,24 @@ impl<'a> Parser<'a> {
         self.saved = Some((span, token));
     }
 
+    // Parses with `f`, or restores the parser and returns none if `f` fails
+    // with a complete input.
+    fn try_parse<O>(&mut self, f: impl FnOnce(&mut Self) -> Result<O>) -> Result<Option<O>> {
+        let saved = self.clone();
+        match f(self) {
+            Ok(x) => Ok(Some(x)),
+            Err(err) if err.kind == ErrorKind::Incomplete => Err(err),
+            Err(_) => {
+                *self = saved;
+                Ok(None)
+            }
+        }
+    }
+
+    fn has_newline(&self, span: Span) -> bool {
+        self.lexer.slice(span).contains('\n')
+    }
+
     fn maybe_kw(&mut self, x: &str) -> Result<Option<Span>> {
         let (span, token) = self.take()?;
         match token {
@@ -70,6 +97,50 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Returns whether the next tokens are the keywords `xs`.
+    fn is_kws(&self, xs: &[&str]) -> Result<bool> {
+        let parser = &mut self.clone();
+        for x in xs {
+            if parser.maybe_kw(x)?.is_none() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Takes the keywords `xs` if they are next, which is how clauses that
+    // start with contextual keywords are found.
+    fn maybe_kws(&mut self, xs: &[&str]) -> Result<Option<Span>> {
+        if !self.is_kws(xs)? {
+            return Ok(None);
+        }
+        let mut span = self.expect_kw(xs[0])?;
+        for x in &xs[1..] {
+            span.end = self.expect_kw(x)?.end;
+        }
+        Ok(Some(span))
+    }
+
+    // Returns whether the next tokens are contextual keywords that start or
+    // end a clause, rather than a name.
+    fn is_contextual_kw(&self) -> Result<bool> {
+        let clauses = [
+            &[LEFT, JOIN][..],
+            &[FULL, JOIN],
+            &[GROUP, BY],
+            &[ORDER, BY],
+            &[THEN],
+            &[ASC],
+            &[DESC],
+        ];
+        for xs in clauses {
+            if self.is_kws(xs)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     fn maybe_punct(&mut self, x: Punct) -> Result<Option<Span>> {
         let (span, token) = self.take()?;
         match token {
@@ -98,6 +169,20 @@ impl<'a> Parser<'a> {
     fn parse_stmt(&mut self, span: Span, token: Token<'a>) -> Result<Stmt<'a>> {
         match token {
             Token::Ident(IMPORT) => self.parse_import_stmt(span.start),
+            Token::Ident(LET) => self.parse_let_stmt(span.start),
+            Token::Ident(FOR) => self.parse_for_stmt(span.start),
+            Token::Ident(WHILE) => self.parse_while_stmt(span.start),
+            Token::Ident(BREAK) if self.loops > 0 => Ok(Stmt::new(span, StmtKind::Break)),
+            Token::Ident(CONTINUE) if self.loops > 0 => Ok(Stmt::new(span, StmtKind::Continue)),
+            Token::Ident(name @ (BREAK | CONTINUE)) => Err(Error::unexpected_token(
+                span,
+                format!("'{name}' outside of a loop"),
+            )),
+            Token::Punct(Punct::LBrace) if !self.is_hash_start()? => {
+                self.save(span, token);
+                let block = self.parse_block()?;
+                Ok(Stmt::new(block.span.clone(), StmtKind::Block(block)))
+            }
             _ => {
                 self.save(span, token);
                 self.parse_expr_stmt()
@@ -105,6 +190,18 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Whether the '{' that was just taken starts a hash rather than a block,
+    // that is if it's followed by '}' or by a field name and a ':'.
+    fn is_hash_start(&self) -> Result<bool> {
+        let parser = &mut self.clone();
+        let (_, token) = parser.take()?;
+        Ok(match token {
+            Token::Punct(Punct::RBrace) => true,
+            Token::Ident(_) | Token::Str(_) => parser.maybe_punct(Punct::Colon)?.is_some(),
+            _ => false,
+        })
+    }
+
     fn parse_expr_stmt(&mut self) -> Result<Stmt<'a>> {
         let expr = self.parse_expr()?;
         Ok(Stmt::new(expr.span.clone(), StmtKind::Expr(expr)))
@@ -114,6 +211,83 @@ impl<'a> Parser<'a> {
         let name = self.parse_ident()?;
         Ok(Stmt::new(start..name.span.end, StmtKind::Import(name)))
     }
+
+    fn parse_let_stmt(&mut self, start: usize) -> Result<Stmt<'a>> {
+        let bind = self.parse_pattern()?;
+        self.expect_punct(Punct::Eq)?;
+        let value = self.parse_expr()?;
+        let span = start..value.span.end;
+        let let_ = Let { bind, value };
+        Ok(Stmt::new(span, StmtKind::Let(let_.into())))
+    }
+
+    fn parse_for_stmt(&mut self, start: usize) -> Result<Stmt<'a>> {
+        let bind = self.parse_pattern()?;
+        self.expect_kw(IN)?;
+        let source = self.parse_expr()?;
+        let body = self.parse_loop_body()?;
+        let span = start..body.span.end;
+        let for_ = For { bind, source, body };
+        Ok(Stmt::new(span, StmtKind::For(for_.into())))
+    }
+
+    fn parse_while_stmt(&mut self, start: usize) -> Result<Stmt<'a>> {
+        let cond = self.parse_expr()?;
+        let body = self.parse_loop_body()?;
+        let span = start..body.span.end;
+        let while_ = While { cond, body };
+        Ok(Stmt::new(span, StmtKind::While(while_.into())))
+    }
+
+    fn parse_loop_body(&mut self) -> Result<Block<'a>> {
+        self.loops += 1;
+        let body = self.parse_block();
+        self.loops -= 1;
+        body
+    }
+
+    fn parse_block(&mut self) -> Result<Block<'a>> {
+        let start = self.expect_punct(Punct::LBrace)?.start;
+        let mut stmts = Vec::new();
+        loop {
+            let (span, token) = self.take()?;
+            match token {
+                Token::Punct(Punct::RBrace) => {
+                    return Ok(Block {
+                        span: start..span.end,
+                        stmts,
+                    })
+                }
+                Token::Punct(Punct::Semi) => continue,
+                _ => {
+                    let stmt = self.parse_stmt(span, token)?;
+                    self.parse_stmt_end(&stmt)?;
+                    stmts.push(stmt);
+                }
+            }
+        }
+    }
+
+    // A statement ends with a ';', a newline, the end of a block, or the end
+    // of input.
+    fn parse_stmt_end(&mut self, stmt: &Stmt<'a>) -> Result<()> {
+        let (span, token) = self.take()?;
+        match token {
+            Token::Punct(Punct::Semi) => Ok(()),
+            Token::Punct(Punct::RBrace) | Token::End => {
+                self.save(span, token);
+                Ok(())
+            }
+            _ if self.has_newline(stmt.span.end..span.start) => {
+                self.save(span, token);
+                Ok(())
+            }
+            _ => {
+                self.save(span.clone(), token.clone());
+                Err(token_error(span, token, "expect ';' or a newline"))
+            }
+        }
+    }
 }
 
 // Expressions
@@ -122,10 +296,14 @@ impl<'a> Parser<'a> {
         let (span, token) = self.take()?;
         let expr = match token {
             Token::Ident(INTO) => self.parse_into_expr(span.start),
-            Token::Ident(FROM) => self.parse_from_expr(span.start),
+            Token::Ident(IF) => self.parse_if_expr(span.start),
+            Token::Ident(FN) => self.parse_func_expr(span.start),
+            Token::Ident(FROM) => self
+                .parse_from_expr(span.start)
+                .and_then(|expr| self.parse_setop_expr(expr)),
             _ => {
                 self.save(span, token);
-                self.parse_lazy_or_expr()
+                self.parse_coalesce_expr()
             }
         }?;
         self.parse_assign_expr(expr)
@@ -140,82 +318,371 @@ impl<'a> Parser<'a> {
     fn parse_into_expr(&mut self, start: usize) -> Result<Expr<'a>> {
         let into = self.parse_expr()?;
         self.expect_kw(INSERT)?;
-        let (span, values) = self.parse_expr_list()?;
-        Ok(Expr::insert(start..span.end, Insert { into, values }))
+        let (mut span, values) = self.parse_expr_list()?;
+        let returning = if self.maybe_kw(RETURNING)?.is_some() {
+            let bind = self.parse_pattern()?;
+            self.expect_punct(Punct::FatArrow)?;
+            let expr = self.parse_expr()?;
+            span.end = expr.span.end;
+            Some((bind, expr))
+        } else {
+            None
+        };
+        Ok(Expr::insert(
+            start..span.end,
+            Insert {
+                into,
+                values,
+                returning,
+            },
+        ))
     }
 
     fn parse_from_expr(&mut self, start: usize) -> Result<Expr<'a>> {
         let from = self.parse_from_clause(start)?;
         if self.maybe_kw(UPDATE)?.is_some() {
-            let (span, exprs) = self.parse_expr_list()?;
-            return Ok(Expr::update(start..span.end, Update { from, exprs }));
+            let (mut span, exprs) = self.parse_expr_list()?;
+            let returning = self.parse_clause(RETURNING, &mut span)?;
+            return Ok(Expr::update(
+                start..span.end,
+                Update {
+                    from,
+                    exprs,
+                    returning,
+                },
+            ));
         }
         if self.maybe_kw(DELETE)?.is_some() {
-            let target = self.parse_ident()?;
+            let target = self.parse_expr()?;
+            let mut span = target.span.clone();
+            let returning = self.parse_clause(RETURNING, &mut span)?;
             return Ok(Expr::delete(
-                start..target.span.end,
-                Delete { from, target },
+                start..span.end,
+                Delete {
+                    from,
+                    target,
+                    returning,
+                },
             ));
         }
         let mut span = start..from.span.end;
-        let project = if self.maybe_kw(SELECT)?.is_some() {
+        let group_by = if self.maybe_kws(&[GROUP, BY])?.is_some() {
             let expr = self.parse_expr()?;
             span.end = expr.span.end;
             Some(expr)
         } else {
             None
         };
-        Ok(Expr::select(span, Select { from, project }))
+        let order_by = self.parse_order_by_clause(&mut span)?;
+        let mut distinct = false;
+        let project = if let Some(kw) = self.maybe_kw(SELECT)? {
+            span.end = kw.end;
+            if let Some(kw) = self.maybe_kw(DISTINCT)? {
+                span.end = kw.end;
+                distinct = true;
+            }
+            // A select without projection is allowed after distinct.
+            let (next, token) = self.take()?;
+            let has_project = !distinct || is_expr_start(&token);
+            self.save(next, token);
+            if has_project {
+                let expr = self.parse_expr()?;
+                span.end = expr.span.end;
+                Some(expr)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        let limit = self.parse_clause(LIMIT, &mut span)?;
+        let offset = self.parse_clause(OFFSET, &mut span)?;
+        let select = Select {
+            from,
+            group_by,
+            order_by,
+            distinct,
+            project,
+            limit,
+            offset,
+        };
+        Ok(Expr::select(span, select))
+    }
+
+    // Parses an optional clause that starts with the keyword `kw`, and
+    // extends `span` to the end of the clause.
+    fn parse_clause(&mut self, kw: &str, span: &mut Span) -> Result<Option<Expr<'a>>> {
+        if self.maybe_kw(kw)?.is_none() {
+            return Ok(None);
+        }
+        let expr = self.parse_expr()?;
+        span.end = expr.span.end;
+        Ok(Some(expr))
+    }
+
+    fn parse_setop_expr(&mut self, mut lhs: Expr<'a>) -> Result<Expr<'a>> {
+        loop {
+            let (span, token) = self.take()?;
+            let op = match token {
+                Token::Ident(UNION) => SetOp::Union,
+                Token::Ident(INTERSECT) => SetOp::Intersect,
+                Token::Ident(EXCEPT) => SetOp::Except,
+                _ => {
+                    self.save(span, token);
+                    return Ok(lhs);
+                }
+            };
+            check_setop_operand(&lhs)?;
+            let start = self.expect_kw(FROM)?.start;
+            let rhs = self.parse_from_expr(start)?;
+            check_setop_operand(&rhs)?;
+            lhs = Expr::setop(Spanned::new(span, op), lhs, rhs);
+        }
+    }
+
+    fn parse_order_by_clause(&mut self, span: &mut Span) -> Result<Vec<SortKey<'a>>> {
+        if self.maybe_kws(&[ORDER, BY])?.is_none() {
+            return Ok(Vec::new());
+        }
+        let keys = self.parse_separated_list(|this| {
+            let expr = this.parse_expr()?;
+            span.end = expr.span.end;
+            let desc = if let Some(kw) = this.maybe_kw(DESC)? {
+                span.end = kw.end;
+                true
+            } else if let Some(kw) = this.maybe_kw(ASC)? {
+                span.end = kw.end;
+                false
+            } else {
+                false
+            };
+            Ok(SortKey { expr, desc })
+        })?;
+        Ok(keys)
     }
 
     fn parse_from_clause(&mut self, start: usize) -> Result<FromClause<'a>> {
-        let bind = self.parse_ident()?;
+        let bind = self.parse_pattern()?;
         self.expect_kw(IN)?;
         let source = self.parse_expr()?;
         let mut span = start..source.span.end;
-        let join = if let Some(join_span) = self.maybe_kw(JOIN)? {
-            let join = self.parse_join_clause(join_span.start)?;
+        let mut joins = Vec::new();
+        // Lateral sources and joins can be mixed, in source order.
+        loop {
+            let join = match self.parse_lateral_clause()? {
+                Some(join) => join,
+                None => match self.parse_join_clause()? {
+                    Some(join) => join,
+                    None => break,
+                },
+            };
             span.end = join.span.end;
-            Some(join)
-        } else {
-            None
-        };
-        let filter = if self.maybe_kw(WHERE)?.is_some() {
-            let expr = self.parse_expr()?;
-            span.end = span.end;
-            Some(expr)
-        } else {
-            None
-        };
+            joins.push(join);
+        }
+        let filter = self.parse_clause(WHERE, &mut span)?;
         Ok(FromClause {
             span,
             bind,
             source,
-            join,
+            joins,
             filter,
         })
     }
 
-    fn parse_join_clause(&mut self, start: usize) -> Result<JoinClause<'a>> {
-        let bind = self.parse_ident()?;
-        self.expect_kw(IN)?;
+    fn parse_pattern(&mut self) -> Result<Pattern<'a>> {
+        let (span, token) = self.take()?;
+        let start = span.start;
+        match token {
+            Token::Ident(name) if is_keyword(name) => {
+                Err(token_error(span, token, format!("'{name}' is a keyword")))
+            }
+            Token::Ident(name) => Ok(Pattern::name(Ident {
+                span,
+                name: name.into(),
+            })),
+            Token::Punct(Punct::LParen) => {
+                let key = self.parse_pattern()?;
+                self.expect_punct(Punct::Comma)?;
+                let value = self.parse_pattern()?;
+                let end = self.expect_punct(Punct::RParen)?;
+                let kind = PatternKind::Pair(key.into(), value.into());
+                Ok(Pattern::new(start..end.end, kind))
+            }
+            Token::Punct(Punct::LBrace) => {
+                let (fields, end) =
+                    self.parse_terminated_list(Punct::RBrace, Self::parse_field_pattern)?;
+                Ok(Pattern::new(start..end.end, PatternKind::Hash(fields)))
+            }
+            Token::Punct(Punct::LBracket) => {
+                let (items, end) =
+                    self.parse_terminated_list(Punct::RBracket, Self::parse_pattern)?;
+                Ok(Pattern::new(start..end.end, PatternKind::List(items)))
+            }
+            _ => {
+                self.save(span.clone(), token.clone());
+                Err(token_error(span, token, "expect a pattern"))
+            }
+        }
+    }
+
+    // `{name}` is short for `{name: name}`.
+    fn parse_field_pattern(&mut self) -> Result<(Field<'a>, Pattern<'a>)> {
+        let (span, token) = self.take()?;
+        if let Token::Ident(name) = token {
+            let field = Field {
+                span: span.clone(),
+                name: name.into(),
+            };
+            if self.maybe_punct(Punct::Colon)?.is_none() {
+                if is_keyword(name) {
+                    return Err(token_error(span, token, format!("'{name}' is a keyword")));
+                }
+                let name = name.into();
+                return Ok((field, Pattern::name(Ident { span, name })));
+            }
+            return Ok((field, self.parse_pattern()?));
+        }
+        self.save(span, token);
+        let field = self.parse_field_name()?;
+        self.expect_punct(Punct::Colon)?;
+        Ok((field, self.parse_pattern()?))
+    }
+
+    // A comma is followed by another source only if a pattern and 'in'
+    // follow it.
+    fn parse_lateral_clause(&mut self) -> Result<Option<JoinClause<'a>>> {
+        // Without a comma, the from clause may end the input, which isn't
+        // incomplete.
+        let (span, token) = self.take()?;
+        let is_comma = matches!(token, Token::Punct(Punct::Comma));
+        self.save(span, token);
+        if !is_comma {
+            return Ok(None);
+        }
+        let bind = self.try_parse(|parser| {
+            parser.expect_punct(Punct::Comma)?;
+            let bind = parser.parse_pattern()?;
+            parser.expect_kw(IN)?;
+            Ok(bind)
+        })?;
+        let Some(bind) = bind else {
+            return Ok(None);
+        };
         let source = self.parse_expr()?;
-        let mut span = start..source.span.end;
-        let filter = if self.maybe_kw(ON)?.is_some() {
-            let expr = self.parse_expr()?;
-            span.end = expr.span.end;
-            Some(expr)
+        Ok(Some(JoinClause {
+            span: bind.span.start..source.span.end,
+            kind: JoinKind::Lateral,
+            bind,
+            source,
+            filter: None,
+        }))
+    }
+
+    fn parse_join_clause(&mut self) -> Result<Option<JoinClause<'a>>> {
+        let (span, kind) = if let Some(span) = self.maybe_kw(JOIN)? {
+            (span, JoinKind::Inner)
+        } else if let Some(span) = self.maybe_kws(&[LEFT, JOIN])? {
+            (span, JoinKind::Left)
+        } else if let Some(span) = self.maybe_kws(&[FULL, JOIN])? {
+            (span, JoinKind::Full)
         } else {
-            None
+            return Ok(None);
         };
-        Ok(JoinClause {
+        let start = span.start;
+        let bind = self.parse_pattern()?;
+        self.expect_kw(IN)?;
+        let source = self.parse_expr()?;
+        let mut span = start..source.span.end;
+        let filter = self.parse_clause(ON, &mut span)?;
+        Ok(Some(JoinClause {
             span,
+            kind,
             bind,
             source,
             filter,
+        }))
+    }
+
+    // The branches are either expressions after 'then' and 'else', or blocks,
+    // where 'else' may be followed by another if expression.
+    fn parse_if_expr(&mut self, start: usize) -> Result<Expr<'a>> {
+        let cond = self.parse_expr()?;
+        let (then, else_) = if self.maybe_kw(THEN)?.is_some() {
+            let then = self.parse_expr()?;
+            let else_ = match self.maybe_kw(ELSE)? {
+                Some(_) => Some(self.parse_expr()?),
+                None => None,
+            };
+            (then, else_)
+        } else {
+            let (span, token) = self.take()?;
+            self.save(span.clone(), token.clone());
+            if !matches!(token, Token::Punct(Punct::LBrace)) {
+                return Err(token_error(span, token, "expect 'then' or '{'"));
+            }
+            let then = self.parse_block()?;
+            let else_ = match self.maybe_kw(ELSE)? {
+                Some(_) => Some(match self.maybe_kw(IF)? {
+                    Some(span) => self.parse_if_expr(span.start)?,
+                    None => Expr::block(self.parse_block()?),
+                }),
+                None => None,
+            };
+            (Expr::block(then), else_)
+        };
+        let end = else_.as_ref().unwrap_or(&then).span.end;
+        Ok(Expr::if_(start..end, If { cond, then, else_ }))
+    }
+
+    fn parse_func_expr(&mut self, start: usize) -> Result<Expr<'a>> {
+        let func = self.parse_func(start)?;
+        let source = self
+            .source
+            .get_or_insert_with(|| self.lexer.input().into())
+            .clone();
+        Ok(Expr::func(func.span.clone(), FuncDef::new(func, source)))
+    }
+
+    fn parse_func(&mut self, start: usize) -> Result<Func<'a>> {
+        let (span, token) = self.take()?;
+        let name = match token {
+            Token::Ident(name) if !is_keyword(name) => Some(Ident {
+                span,
+                name: name.into(),
+            }),
+            _ => {
+                self.save(span, token);
+                None
+            }
+        };
+        self.expect_punct(Punct::LParen)?;
+        let (params, _) = self.parse_terminated_list(Punct::RParen, Self::parse_pattern)?;
+        self.expect_punct(Punct::FatArrow)?;
+        // A loop around a function doesn't continue from its body.
+        let loops = std::mem::take(&mut self.loops);
+        let body = self.parse_func_body();
+        self.loops = loops;
+        let body = body?;
+        Ok(Func {
+            span: start..body.span.end,
+            name,
+            params,
+            body,
         })
     }
 
+    // The body is a block if it starts with a '{' that doesn't start a hash.
+    fn parse_func_body(&mut self) -> Result<Expr<'a>> {
+        let (span, token) = self.take()?;
+        let is_block = matches!(token, Token::Punct(Punct::LBrace)) && !self.is_hash_start()?;
+        self.save(span, token);
+        if is_block {
+            self.parse_block().map(Expr::block)
+        } else {
+            self.parse_expr()
+        }
+    }
+
     fn parse_assign_expr(&mut self, expr: Expr<'a>) -> Result<Expr<'a>> {
         let (span, token) = self.take()?;
         let kind = match token {
@@ -242,6 +709,15 @@ impl<'a> Parser<'a> {
         Ok(Expr::compound_assign(Spanned::new(span, kind), expr, value))
     }
 
+    fn parse_coalesce_expr(&mut self) -> Result<Expr<'a>> {
+        let mut lhs = self.parse_lazy_or_expr()?;
+        while self.maybe_punct(Punct::QuestionQuestion)?.is_some() {
+            let rhs = self.parse_lazy_or_expr()?;
+            lhs = Expr::coalesce(lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
     fn parse_lazy_or_expr(&mut self) -> Result<Expr<'a>> {
         let mut lhs = self.parse_lazy_and_expr()?;
         while let Some(span) = self.maybe_punct(Punct::OrOr)? {
@@ -283,14 +759,70 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_cmp_expr(&mut self) -> Result<Expr<'a>> {
-        let mut lhs = self.parse_or_expr()?;
+        let mut lhs = self.parse_range_expr()?;
         while let Some(op) = self.parse_cmp_op()? {
-            let rhs = self.parse_or_expr()?;
+            let rhs = self.parse_range_expr()?;
             lhs = Expr::cmpop(op, lhs, rhs);
         }
         Ok(lhs)
     }
 
+    fn parse_range_op(&mut self) -> Result<Option<Spanned<bool>>> {
+        let (span, token) = self.take()?;
+        let inclusive = match token {
+            Token::Punct(Punct::DotDot) => false,
+            Token::Punct(Punct::DotDotEq) => true,
+            _ => {
+                self.save(span, token);
+                return Ok(None);
+            }
+        };
+        Ok(Some(Spanned::new(span, inclusive)))
+    }
+
+    fn parse_range_expr(&mut self) -> Result<Expr<'a>> {
+        if let Some(op) = self.parse_range_op()? {
+            return self.parse_range_end(op.span.start, None, op);
+        }
+        let start = self.parse_or_expr()?;
+        match self.parse_range_op()? {
+            Some(op) => self.parse_range_end(start.span.start, Some(start), op),
+            None => Ok(start),
+        }
+    }
+
+    fn parse_range_end(
+        &mut self,
+        start: usize,
+        lhs: Option<Expr<'a>>,
+        op: Spanned<bool>,
+    ) -> Result<Expr<'a>> {
+        let (span, token) = self.take()?;
+        // A brace after an open-ended range starts a block, since a hash
+        // can't be the end of a range, a negation on the next line starts a
+        // new statement, and a contextual keyword after it starts or ends a
+        // clause.
+        let is_end = is_expr_start(&token)
+            && !matches!(token, Token::Punct(Punct::LBrace))
+            && !(matches!(token, Token::Punct(Punct::Minus))
+                && self.has_newline(op.span.end..span.start));
+        self.save(span, token);
+        let has_end = op.kind || (is_end && !self.is_contextual_kw()?);
+        // An exclusive range without an end is open-ended.
+        let end = if has_end {
+            Some(self.parse_or_expr()?)
+        } else {
+            None
+        };
+        let span = start..end.as_ref().map_or(op.span.end, |x| x.span.end);
+        let range = Range {
+            start: lhs,
+            end,
+            inclusive: op.kind,
+        };
+        Ok(Expr::range(span, range))
+    }
+
     fn parse_or_expr(&mut self) -> Result<Expr<'a>> {
         let mut lhs = self.parse_xor_expr()?;
         while let Some(span) = self.maybe_punct(Punct::Or)? {
@@ -340,11 +872,12 @@ impl<'a> Parser<'a> {
         Ok(lhs)
     }
 
-    fn parse_add_op(&mut self) -> Result<Option<Spanned<BinOp>>> {
+    fn parse_add_op(&mut self, lhs: &Expr<'a>) -> Result<Option<Spanned<BinOp>>> {
         let (span, token) = self.take()?;
         let kind = match token {
             Token::Punct(Punct::Plus) => BinOp::Add,
-            Token::Punct(Punct::Minus) => BinOp::Sub,
+            // A negation on the next line starts a new statement.
+            Token::Punct(Punct::Minus) if !self.has_newline(lhs.span.end..span.start) => BinOp::Sub,
             _ => {
                 self.save(span, token);
                 return Ok(None);
@@ -355,7 +888,7 @@ impl<'a> Parser<'a> {
 
     fn parse_add_expr(&mut self) -> Result<Expr<'a>> {
         let mut lhs = self.parse_mul_expr()?;
-        while let Some(op) = self.parse_add_op()? {
+        while let Some(op) = self.parse_add_op(&lhs)? {
             let rhs = self.parse_mul_expr()?;
             lhs = Expr::binop(op, lhs, rhs);
         }
@@ -404,6 +937,13 @@ impl<'a> Parser<'a> {
         loop {
             let (span, token) = self.take()?;
             match token {
+                // A call or index on the next line starts a new statement.
+                Token::Punct(Punct::LParen | Punct::LBracket)
+                    if self.has_newline(expr.span.end..span.start) =>
+                {
+                    self.save(span, token);
+                    return Ok(expr);
+                }
                 Token::Punct(Punct::LParen) => {
                     let (args, paren) =
                         self.parse_terminated_list(Punct::RParen, Self::parse_expr)?;
@@ -418,6 +958,10 @@ impl<'a> Parser<'a> {
                     let field = self.parse_field_name()?;
                     expr = Expr::field(expr, field);
                 }
+                Token::Punct(Punct::QuestionDot) => {
+                    let field = self.parse_field_name()?;
+                    expr = Expr::safe_field(expr, field);
+                }
                 _ => {
                     self.save(span, token);
                     return Ok(expr);
@@ -430,8 +974,9 @@ impl<'a> Parser<'a> {
         let (span, token) = self.take()?;
         match token {
             Token::Str(s) => Ok(Expr::lit(span, LitKind::Str(s))),
-            Token::Int(s, radix) => Ok(Expr::lit(span, LitKind::Int(s, radix))),
-            Token::Float(s) => Ok(Expr::lit(span, LitKind::Float(s))),
+            Token::FStr(parts) => self.parse_format_expr(span, parts),
+            Token::Int(s, radix) => Ok(Expr::lit(span, LitKind::Int(s.into(), radix))),
+            Token::Float(s) => Ok(Expr::lit(span, LitKind::Float(s.into()))),
             Token::Ident(NULL) => Ok(Expr::lit(span, LitKind::Null)),
             Token::Ident(TRUE) => Ok(Expr::lit(span, LitKind::Bool(true))),
             Token::Ident(FALSE) => Ok(Expr::lit(span, LitKind::Bool(false))),
@@ -446,10 +991,49 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_format_expr(&mut self, span: Span, parts: Vec<FStrPart<'a>>) -> Result<Expr<'a>> {
+        let parts = parts
+            .into_iter()
+            .map(|part| match part {
+                FStrPart::Str(s) => Ok(FormatPart::Str(s)),
+                FStrPart::Expr(span) => self.parse_embedded_expr(span).map(FormatPart::Expr),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Expr::format(span, parts))
+    }
+
+    fn parse_embedded_expr(&self, span: Span) -> Result<Expr<'a>> {
+        let mut parser = Parser {
+            lexer: self.lexer.fork(span),
+            saved: None,
+            source: self.source.clone(),
+            loops: self.loops,
+        };
+        // The embedded expression can't be completed by more input.
+        parser.parse_expr_to_end().map_err(|mut e| {
+            if e.kind == ErrorKind::Incomplete {
+                e.kind = ErrorKind::UnexpectedToken;
+            }
+            e
+        })
+    }
+
+    fn parse_expr_to_end(&mut self) -> Result<Expr<'a>> {
+        let expr = self.parse_expr()?;
+        let (span, token) = self.take()?;
+        match token {
+            Token::End => Ok(expr),
+            _ => Err(token_error(span, token, "expect '}'")),
+        }
+    }
+
     fn parse_ident(&mut self) -> Result<Ident<'a>> {
         let (span, token) = self.take()?;
         match token {
-            Token::Ident(name) => Ok(Ident { span, name }),
+            Token::Ident(name) => Ok(Ident {
+                span,
+                name: name.into(),
+            }),
             _ => {
                 self.save(span.clone(), token.clone());
                 Err(token_error(span, token, "expect an identifier"))
@@ -467,7 +1051,7 @@ impl<'a> Parser<'a> {
         let (span, token) = self.take()?;
         let name = match token {
             Token::Str(s) => s,
-            Token::Ident(s) => s,
+            Token::Ident(s) => s.into(),
             _ => {
                 self.save(span.clone(), token.clone());
                 return Err(token_error(span, token, "expect a field name"));
@@ -528,14 +1112,44 @@ impl<'a> Iterator for Parser<'a> {
     type Item = Result<Stmt<'a>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.take() {
-            Ok((_, Token::End)) => None,
-            Ok((span, token)) => Some(self.parse_stmt(span, token)),
-            Err(err) => Some(Err(err)),
+        loop {
+            return match self.take() {
+                Ok((_, Token::End)) => None,
+                Ok((_, Token::Punct(Punct::Semi))) => continue,
+                Ok((span, token)) => Some(
+                    self.parse_stmt(span, token)
+                        .and_then(|stmt| self.parse_stmt_end(&stmt).map(|_| stmt)),
+                ),
+                Err(err) => Some(Err(err)),
+            };
         }
     }
 }
 
+fn is_expr_start(token: &Token) -> bool {
+    match token {
+        Token::End => false,
+        Token::Ident(name) => !is_keyword(name) || matches!(*name, NULL | TRUE | FALSE | IF | FN),
+        Token::Str(_) | Token::FStr(_) | Token::Int(..) | Token::Float(_) => true,
+        Token::Punct(p) => matches!(
+            p,
+            Punct::LParen | Punct::LBrace | Punct::LBracket | Punct::Minus | Punct::Not
+        ),
+    }
+}
+
+// Only select expressions can be operands of set operations, since the other
+// from expressions modify their sources.
+fn check_setop_operand(expr: &Expr) -> Result<()> {
+    match expr.kind {
+        ExprKind::Select(_) | ExprKind::SetOp(..) => Ok(()),
+        _ => Err(Error::unexpected_token(
+            expr.span.clone(),
+            "expect a select expression as an operand of a set operation",
+        )),
+    }
+}
+
 fn token_error(span: Span, token: Token, message: impl ToString) -> Error {
     if matches!(token, Token::End) {
         Error::incomplete(span, message)
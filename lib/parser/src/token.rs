@@ -1,6 +1,7 @@
+use std::borrow::Cow;
 use std::fmt;
 
-use crate::Radix;
+use crate::{Radix, Span};
 
 #[derive(Clone, Debug)]
 pub(crate) enum Token<'a> {
@@ -11,7 +12,10 @@ pub(crate) enum Token<'a> {
     Ident(&'a str),
 
     // String literal
-    Str(&'a str),
+    Str(Cow<'a, str>),
+
+    // Formatted string literal
+    FStr(Vec<FStrPart<'a>>),
 
     // Integer literal
     Int(&'a str, Radix),
@@ -23,6 +27,13 @@ pub(crate) enum Token<'a> {
     Punct(Punct),
 }
 
+#[derive(Clone, Debug)]
+pub(crate) enum FStrPart<'a> {
+    Str(Cow<'a, str>),
+    // The span of an embedded expression
+    Expr(Span),
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum Punct {
     Semi,
@@ -30,6 +41,7 @@ pub(crate) enum Punct {
     Comma,
     Dot,
     DotDot,
+    DotDotEq,
     LParen,
     RParen,
     LBrace,
@@ -38,6 +50,7 @@ pub(crate) enum Punct {
     RBracket,
     Eq,
     EqEq,
+    FatArrow,
     Or,
     OrEq,
     OrOr,
@@ -66,6 +79,8 @@ pub(crate) enum Punct {
     SlashEq,
     Percent,
     PercentEq,
+    QuestionDot,
+    QuestionQuestion,
 }
 
 impl Punct {
@@ -77,6 +92,7 @@ impl Punct {
             Comma => ",",
             Dot => ".",
             DotDot => "..",
+            DotDotEq => "..=",
             LParen => "(",
             RParen => ")",
             LBrace => "{",
@@ -85,6 +101,7 @@ impl Punct {
             RBracket => "]",
             Eq => "=",
             EqEq => "==",
+            FatArrow => "=>",
             Not => "!",
             NotEq => "!=",
             Plus => "+",
@@ -97,6 +114,8 @@ impl Punct {
             SlashEq => "/=",
             Percent => "%",
             PercentEq => "%=",
+            QuestionDot => "?.",
+            QuestionQuestion => "??",
             Or => "|",
             OrEq => "|=",
             OrOr => "||",
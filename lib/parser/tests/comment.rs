@@ -0,0 +1,43 @@
+mod util;
+use util::*;
+
+use sigma_parser::ast::{ExprKind, LitKind, StmtKind};
+use sigma_parser::ErrorKind;
+
+#[test]
+fn line_comments() {
+    let stmts = parse("# comment\na = 1 # comment\nb = 2 // comment\n// comment");
+    assert_eq!(stmts.len(), 2);
+}
+
+#[test]
+fn block_comments() {
+    assert_eq!(parse("a = /* comment */ 1; b = 2").len(), 2);
+    assert_eq!(parse("a = 1 /* a comment\nover lines */ + 2").len(), 1);
+    assert_eq!(parse("/* outer /* nested */ still a comment */ a").len(), 1);
+}
+
+#[test]
+fn unterminated_block_comment() {
+    let (kind, span, message) = parse_err("a = 1 /* outer /* nested */");
+    assert_eq!(kind, ErrorKind::Incomplete);
+    assert_eq!(span, "/* outer /* nested */");
+    assert_eq!(message, "unterminated block comment");
+}
+
+#[test]
+fn comment_markers_in_strings_and_operators() {
+    let stmts = parse(r##""# not // a /* comment""##);
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+        panic!("expect an expression");
+    };
+    let ExprKind::Lit(lit) = &expr.kind else {
+        panic!("expect a literal");
+    };
+    assert!(matches!(&lit.kind, LitKind::Str(s) if s == "# not // a /* comment"));
+    let stmts = parse("a / b");
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+        panic!("expect an expression");
+    };
+    assert!(matches!(expr.kind, ExprKind::BinOp(..)));
+}
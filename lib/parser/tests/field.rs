@@ -0,0 +1,26 @@
+mod util;
+use util::*;
+
+use sigma_parser::ast::{ExprKind, StmtKind};
+use sigma_parser::ErrorKind;
+
+#[test]
+fn safe_field_chain() {
+    let stmts = parse("a?.b.c");
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+        panic!("expect an expression");
+    };
+    let ExprKind::Field(inner, field) = &expr.kind else {
+        panic!("expect a field access");
+    };
+    assert_eq!(field.name, "c");
+    assert!(matches!(inner.kind, ExprKind::SafeField(..)));
+}
+
+#[test]
+fn lone_question_mark() {
+    let (kind, span, message) = parse_err("a ? b");
+    assert_eq!(kind, ErrorKind::InvalidToken);
+    assert_eq!(span, "?");
+    assert_eq!(message, "expected '?.' or '??'");
+}
@@ -0,0 +1,83 @@
+mod util;
+use util::*;
+
+use sigma_parser::ast::{ExprKind, JoinKind, StmtKind};
+
+fn join_kinds(src: &str) -> Vec<JoinKind> {
+    let stmts = parse(src);
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+        panic!("expect an expression");
+    };
+    let ExprKind::Select(select) = &expr.kind else {
+        panic!("expect a select expression");
+    };
+    select.from.joins.iter().map(|join| join.kind).collect()
+}
+
+#[test]
+fn joins_and_laterals_in_source_order() {
+    let src = "from x in a join y in b on x == y, z in y.items left join w in c, v in w";
+    assert_eq!(
+        join_kinds(src),
+        [
+            JoinKind::Inner,
+            JoinKind::Lateral,
+            JoinKind::Left,
+            JoinKind::Lateral
+        ]
+    );
+}
+
+#[test]
+fn from_at_end_of_input() {
+    assert_eq!(join_kinds("from x in a"), []);
+    assert_eq!(join_kinds("from x in a, y in x"), [JoinKind::Lateral]);
+}
+
+#[test]
+fn comma_without_source() {
+    let stmts = parse("f(from x in a, b)");
+    assert_eq!(stmts.len(), 1);
+}
+
+#[test]
+fn contextual_keywords_as_names() {
+    parse("order = 1; by = 2; group = 3; asc = 4; desc = 5; then = 6");
+    parse("from order in rows group by order.by order by group desc select asc + then");
+    parse("if then then order else by");
+}
+
+#[test]
+fn contextual_keywords_after_open_range() {
+    let stmts = parse("from x in 0.. order by x desc select x");
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+        panic!("expect an expression");
+    };
+    let ExprKind::Select(select) = &expr.kind else {
+        panic!("expect a select expression");
+    };
+    assert_eq!(select.order_by.len(), 1);
+    parse("if x in 1.. then x else 0");
+}
+
+#[test]
+fn keyword_in_pattern() {
+    let (_, span, message) = parse_err("from {in} in rows select 1");
+    assert_eq!(span, "in");
+    assert_eq!(message, "'in' is a keyword");
+    let (_, span, message) = parse_err("for select in rows { }");
+    assert_eq!(span, "select");
+    assert_eq!(message, "'select' is a keyword");
+    parse("from {in: x} in rows select x");
+}
+
+#[test]
+fn join_kinds_as_names() {
+    parse("left = 1; full = 2");
+    assert_eq!(
+        join_kinds(
+            "from left in a left join full in b on left == full full join c in 0.. on c == left"
+        ),
+        [JoinKind::Left, JoinKind::Full]
+    );
+}
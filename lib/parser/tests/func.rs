@@ -0,0 +1,69 @@
+mod util;
+use util::*;
+
+use sigma_parser::ast::{ExprKind, StmtKind};
+
+#[test]
+fn function_outlives_input() {
+    let def = {
+        let src = String::from("fn add(x, y) => x + y");
+        let stmts = parse(&src);
+        let StmtKind::Expr(expr) = &stmts[0].kind else {
+            panic!("expect an expression");
+        };
+        let ExprKind::Func(def) = &expr.kind else {
+            panic!("expect a function");
+        };
+        def.clone()
+    };
+    let func = def.func();
+    assert_eq!(
+        func.name.as_ref().map(|ident| ident.name.as_ref()),
+        Some("add")
+    );
+    assert_eq!(func.params.len(), 2);
+    assert_eq!(&def.source()[func.body.span.clone()], "x + y");
+}
+
+#[test]
+fn functions_share_the_source() {
+    let src = "f = fn(x) => x; g = fn(y) => fn(z) => y";
+    let defs: Vec<_> = parse(src)
+        .iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StmtKind::Expr(expr) => match &expr.kind {
+                ExprKind::Assign(_, value) => match &value.kind {
+                    ExprKind::Func(def) => Some(def.clone()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(defs.len(), 2);
+    assert!(std::rc::Rc::ptr_eq(defs[0].source(), defs[1].source()));
+}
+
+#[test]
+fn block_body() {
+    let stmts = parse("fn f(x) => { let y = x; y }");
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+        panic!("expect an expression");
+    };
+    let ExprKind::Func(def) = &expr.kind else {
+        panic!("expect a function");
+    };
+    let ExprKind::Block(block) = &def.func().body.kind else {
+        panic!("expect a block body");
+    };
+    assert_eq!(block.stmts.len(), 2);
+}
+
+#[test]
+fn nested_functions() {
+    // Each function is parsed once, so deep nesting parses quickly.
+    let src = format!("f = {}1", "fn(x) => ".repeat(64));
+    let stmts = parse(&src);
+    assert_eq!(stmts.len(), 1);
+}
@@ -0,0 +1,33 @@
+mod util;
+use util::*;
+
+use sigma_parser::ErrorKind;
+
+#[test]
+fn input_that_needs_more_lines() {
+    for src in [
+        "a = ",
+        "1 +",
+        "[1, 2,",
+        "{a: 1,",
+        "f(1,",
+        "from x in",
+        "from x in list where x > 1 select",
+        "if x {",
+        "fn f(x) =>",
+        "for x in list {\n  x",
+        "\"\"\"multiline\nstring",
+        "/* block\ncomment",
+    ] {
+        let (kind, _, message) = parse_err(src);
+        assert_eq!(kind, ErrorKind::Incomplete, "{src:?}: {message}");
+    }
+}
+
+#[test]
+fn input_that_is_wrong() {
+    for src in ["1 + )", "\"unterminated", "a b", "[1 2]", "from 1 in x"] {
+        let (kind, _, message) = parse_err(src);
+        assert_ne!(kind, ErrorKind::Incomplete, "{src:?}: {message}");
+    }
+}
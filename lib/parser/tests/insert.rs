@@ -0,0 +1,15 @@
+mod util;
+use util::*;
+
+#[test]
+fn returning_pattern() {
+    parse("into list insert 1, 2 returning x => x * 2");
+    parse("into list insert {a: 1} returning {a} => a");
+}
+
+#[test]
+fn returning_without_pattern() {
+    let (_, span, message) = parse_err("into list insert 1 returning x.a");
+    assert_eq!(span, ".");
+    assert_eq!(message, "expect '=>'");
+}
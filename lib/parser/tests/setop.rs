@@ -0,0 +1,29 @@
+mod util;
+use util::*;
+
+use sigma_parser::ErrorKind;
+
+#[test]
+fn select_operands() {
+    parse("from x in a union from y in b select y except from z in c where z > 1");
+}
+
+#[test]
+fn delete_operand() {
+    let src = "from x in a union from y in b where y == 1 delete y";
+    let (kind, span, message) = parse_err(src);
+    assert_eq!(kind, ErrorKind::UnexpectedToken);
+    assert_eq!(span, "from y in b where y == 1 delete y");
+    assert_eq!(
+        message,
+        "expect a select expression as an operand of a set operation"
+    );
+}
+
+#[test]
+fn update_operand() {
+    let src = "from x in a update x.n = 1 intersect from y in b";
+    let (kind, span, _) = parse_err(src);
+    assert_eq!(kind, ErrorKind::UnexpectedToken);
+    assert_eq!(span, "from x in a update x.n = 1");
+}
@@ -0,0 +1,79 @@
+mod util;
+use util::*;
+
+use sigma_parser::ast::{ExprKind, StmtKind};
+use sigma_parser::ErrorKind;
+
+#[test]
+fn brace_starts_block_statement() {
+    let stmts = parse("{ a = 1; b = 2 }");
+    let StmtKind::Block(block) = &stmts[0].kind else {
+        panic!("expect a block");
+    };
+    assert_eq!(block.stmts.len(), 2);
+
+    let stmts = parse("{\n  let a = 1\n  a\n}");
+    assert!(matches!(stmts[0].kind, StmtKind::Block(_)));
+}
+
+#[test]
+fn brace_starts_hash_statement() {
+    for src in ["{}", "{a: 1}", r#"{"a": 1}.a"#] {
+        let stmts = parse(src);
+        let StmtKind::Expr(expr) = &stmts[0].kind else {
+            panic!("expect an expression in {src:?}");
+        };
+        assert!(
+            matches!(expr.kind, ExprKind::Hash(_) | ExprKind::Field(..)),
+            "{src:?}"
+        );
+    }
+}
+
+#[test]
+fn break_outside_loop() {
+    let (kind, span, message) = parse_err("break");
+    assert_eq!(kind, ErrorKind::UnexpectedToken);
+    assert_eq!(span, "break");
+    assert_eq!(message, "'break' outside of a loop");
+
+    let (_, span, message) = parse_err("{ continue }");
+    assert_eq!(span, "continue");
+    assert_eq!(message, "'continue' outside of a loop");
+}
+
+#[test]
+fn break_inside_loop() {
+    parse("for x in [1] { if x > 0 { break } else { continue } }");
+    parse("while true { { break } }");
+}
+
+#[test]
+fn break_in_function_inside_loop() {
+    let (_, span, message) = parse_err("while true { f = fn() => if true { break } }");
+    assert_eq!(span, "break");
+    assert_eq!(message, "'break' outside of a loop");
+
+    parse("f = fn() => if true { for x in [1] { break } }");
+}
+
+#[test]
+fn statement_separators() {
+    assert_eq!(parse("a = 1; b = 2\nc = 3").len(), 3);
+    let (kind, span, message) = parse_err("a = 1 b = 2");
+    assert_eq!(kind, ErrorKind::UnexpectedToken);
+    assert_eq!(span, "b");
+    assert_eq!(message, "expect ';' or a newline");
+}
+
+#[test]
+fn next_line_starts_statement() {
+    // A call, an index or a negation on the next line starts a new
+    // statement, and other operators continue the previous one.
+    assert_eq!(parse("f\n(1)").len(), 2);
+    assert_eq!(parse("a\n[1]").len(), 2);
+    assert_eq!(parse("a = 1\n-1").len(), 2);
+    assert_eq!(parse("a = 0..\n-1").len(), 2);
+    assert_eq!(parse("a = 1\n+ 1").len(), 1);
+    assert_eq!(parse("a = 1 -\n1").len(), 1);
+}
@@ -0,0 +1,83 @@
+mod util;
+use util::*;
+
+use sigma_parser::ast::{ExprKind, LitKind, StmtKind};
+use sigma_parser::ErrorKind;
+
+// Parses a string literal and returns its value.
+fn str_lit(src: &str) -> String {
+    let stmts = parse(src);
+    let StmtKind::Expr(expr) = &stmts[0].kind else {
+        panic!("expect an expression");
+    };
+    match &expr.kind {
+        ExprKind::Lit(lit) => match &lit.kind {
+            LitKind::Str(s) => s.to_string(),
+            kind => panic!("expect a string literal, not {kind:?}"),
+        },
+        kind => panic!("expect a literal, not {kind:?}"),
+    }
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(str_lit(r#""a\nb\tc\r\0""#), "a\nb\tc\r\0");
+    assert_eq!(str_lit(r#""\"q\" \'q\' \\""#), "\"q\" 'q' \\");
+    assert_eq!(str_lit(r#""\u{48}\u{e9}\u{1F600}""#), "Hé😀");
+    assert_eq!(str_lit(r#""""#), "");
+}
+
+#[test]
+fn invalid_escapes() {
+    let (kind, span, message) = parse_err(r#""a\qb""#);
+    assert_eq!(kind, ErrorKind::InvalidToken);
+    assert_eq!(span, r"\q");
+    assert_eq!(message, r"invalid escape sequence '\q'");
+
+    let (_, span, message) = parse_err(r#""\u{110000}""#);
+    assert_eq!(span, r"\u{110000}");
+    assert_eq!(message, "invalid unicode character '110000'");
+
+    for src in [r#""\u{}""#, r#""\u{1234567}""#, r#""\u41""#] {
+        let (kind, _, message) = parse_err(src);
+        assert_eq!(kind, ErrorKind::InvalidToken, "{src}");
+        assert!(
+            message.starts_with("invalid unicode escape"),
+            "{src}: {message}"
+        );
+    }
+}
+
+#[test]
+fn raw_strings() {
+    assert_eq!(str_lit(r#"r"a\nb\q""#), r"a\nb\q");
+    assert_eq!(str_lit(r#"r"""a "quoted" \n""""#), r#"a "quoted" \n"#);
+}
+
+#[test]
+fn multiline_strings() {
+    let src = "\"\"\"first\n\"second\" \\t\nthird\"\"\"";
+    assert_eq!(str_lit(src), "first\n\"second\" \t\nthird");
+}
+
+#[test]
+fn unterminated_strings() {
+    // Only a multiline string may continue on the next line.
+    let (kind, _, message) = parse_err("\"abc");
+    assert_eq!(kind, ErrorKind::InvalidToken);
+    assert_eq!(message, "unterminated string literal");
+    let (kind, span, _) = parse_err("a = \"\"\"abc\n");
+    assert_eq!(kind, ErrorKind::Incomplete);
+    assert_eq!(span, "\"\"\"abc\n");
+}
+
+#[test]
+fn format_string_errors() {
+    let (kind, span, message) = parse_err(r#"f"a}""#);
+    assert_eq!(kind, ErrorKind::InvalidToken);
+    assert_eq!(span, "}");
+    assert_eq!(message, "single '}' in f-string, use '}}' instead");
+    let (_, span, message) = parse_err(r#"f"{a"#);
+    assert_eq!(span, "{a");
+    assert_eq!(message, "unterminated f-string expression");
+}
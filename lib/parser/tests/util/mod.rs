@@ -0,0 +1,19 @@
+#![allow(dead_code)]
+
+use sigma_parser::{ast::Stmt, ErrorKind, Parser};
+
+/// Parses all statements, and panics if any fails to parse.
+pub fn parse(src: &str) -> Vec<Stmt<'_>> {
+    Parser::new(src)
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| panic!("{} at {:?}", e.message, &src[e.span]))
+}
+
+/// Parses all statements and returns the kind of the first error, the source
+/// at its span and its message.
+pub fn parse_err(src: &str) -> (ErrorKind, &str, String) {
+    match Parser::new(src).collect::<Result<Vec<_>, _>>() {
+        Ok(_) => panic!("expect an error"),
+        Err(e) => (e.kind, &src[e.span], e.message),
+    }
+}
@@ -0,0 +1,82 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use sigma_parser::ast::BinOp;
+
+use crate::{Error, Object, Result};
+
+/// Returns the aggregate functions.
+///
+/// Each function takes a collection. In a group by select, an aggregate call
+/// collects its argument over the rows of each group instead, or the rows
+/// themselves if there is no argument.
+pub(crate) fn functions() -> HashMap<String, Object> {
+    let mut functions = HashMap::new();
+    functions.insert("count".into(), count.into());
+    functions.insert("sum".into(), sum.into());
+    functions.insert("avg".into(), avg.into());
+    functions.insert("min".into(), min.into());
+    functions.insert("max".into(), max.into());
+    functions.insert("collect".into(), collect.into());
+    functions
+}
+
+fn collection(args: &[Object]) -> Result<&Object> {
+    match args {
+        [x] => Ok(x),
+        [] => Err(Error::new(
+            "aggregate function called outside of a group expects 1 argument",
+        )),
+        _ => Err(Error::new("expect 1 argument")),
+    }
+}
+
+fn count(_: &Object, args: &[Object]) -> Result<Object> {
+    Ok((collection(args)?.iter()?.count() as i64).into())
+}
+
+fn sum(_: &Object, args: &[Object]) -> Result<Object> {
+    let mut iter = collection(args)?.iter()?;
+    match iter.next() {
+        Some(first) => iter.try_fold(first, |acc, x| acc.binop(BinOp::Add, &x)),
+        None => Ok(0.into()),
+    }
+}
+
+fn avg(_: &Object, args: &[Object]) -> Result<Object> {
+    let mut total = 0.0;
+    let mut count = 0;
+    for x in collection(args)?.iter()? {
+        total += x
+            .as_f64()
+            .or_else(|| x.as_i64().map(|x| x as f64))
+            .ok_or_else(|| Error::new(format!("cannot average '{}'", x.type_name())))?;
+        count += 1;
+    }
+    if count == 0 {
+        return Ok(().into());
+    }
+    Ok((total / count as f64).into())
+}
+
+fn min(_: &Object, args: &[Object]) -> Result<Object> {
+    extremum(collection(args)?, Ordering::Less)
+}
+
+fn max(_: &Object, args: &[Object]) -> Result<Object> {
+    extremum(collection(args)?, Ordering::Greater)
+}
+
+fn extremum(this: &Object, ord: Ordering) -> Result<Object> {
+    let mut iter = this.iter()?;
+    let Some(first) = iter.next() else {
+        return Ok(().into());
+    };
+    iter.try_fold(first, |acc, x| {
+        Ok(if x.compare(&acc)? == ord { x } else { acc })
+    })
+}
+
+fn collect(_: &Object, args: &[Object]) -> Result<Object> {
+    Ok(collection(args)?.iter()?.collect::<Vec<_>>().into())
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
+
+use crate::{Error, Object, Result};
+
+/// Returns the functions on hashes.
+pub(crate) fn functions() -> HashMap<String, Object> {
+    let mut functions = HashMap::new();
+    functions.insert("keys".into(), keys.into());
+    functions.insert("values".into(), values.into());
+    functions
+}
+
+fn hash(args: &[Object]) -> Result<&IndexMap<String, Object>> {
+    match args {
+        [x] => x
+            .as_hash()
+            .ok_or_else(|| Error::new(format!("expect a 'hash', not '{}'", x.type_name()))),
+        _ => Err(Error::new("expect 1 argument")),
+    }
+}
+
+fn keys(_: &Object, args: &[Object]) -> Result<Object> {
+    let keys: Vec<Object> = hash(args)?.keys().map(|k| k.as_str().into()).collect();
+    Ok(keys.into())
+}
+
+fn values(_: &Object, args: &[Object]) -> Result<Object> {
+    let values: Vec<Object> = hash(args)?.values().cloned().collect();
+    Ok(values.into())
+}
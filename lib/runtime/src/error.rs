@@ -1,9 +1,33 @@
+use std::rc::Rc;
+
 use sigma_parser::Span;
 
 #[derive(Debug)]
 pub struct Error {
     pub span: Span,
     pub message: String,
+    /// The input that `span` refers to, if the error is in a function that
+    /// may be defined in an earlier input.
+    pub source: Option<Rc<str>>,
+    /// Secondary labels, such as the calls of the function with the error.
+    pub labels: Vec<Label>,
+    // Set if the error is a break or continue to be caught by a loop. The
+    // parser only accepts them inside loops, so they never reach the caller.
+    pub(crate) flow: Option<Flow>,
+}
+
+#[derive(Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// The input that `span` refers to, like the source of an error.
+    pub source: Option<Rc<str>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Flow {
+    Break,
+    Continue,
 }
 
 impl Error {
@@ -15,8 +39,40 @@ impl Error {
         Self {
             span,
             message: message.to_string(),
+            source: None,
+            labels: Vec::new(),
+            flow: None,
         }
     }
+
+    pub(crate) fn flow(span: Span, flow: Flow) -> Self {
+        let message = match flow {
+            Flow::Break => "'break' outside of a loop",
+            Flow::Continue => "'continue' outside of a loop",
+        };
+        Self {
+            flow: Some(flow),
+            ..Self::with_span(span, message)
+        }
+    }
+
+    // Sets the input of the spans that don't have one yet.
+    pub(crate) fn in_source(mut self, source: &Rc<str>) -> Self {
+        self.source.get_or_insert_with(|| source.clone());
+        for label in &mut self.labels {
+            label.source.get_or_insert_with(|| source.clone());
+        }
+        self
+    }
+
+    pub(crate) fn with_label(mut self, span: Span, message: impl ToString) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+            source: None,
+        });
+        self
+    }
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
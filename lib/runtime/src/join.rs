@@ -0,0 +1,212 @@
+use sigma_parser::ast::*;
+
+use crate::{Object, ObjectMap, Result, Runtime, Vars};
+
+/// A join clause with its evaluated items.
+///
+/// If the on clause contains an equality between an expression over the
+/// names bound before the join and one over the join name, the items are
+/// indexed by the latter on first use, so matching items are found by a hash
+/// lookup instead of checking every item.
+///
+/// A lateral join has no source or items, since its source is evaluated for
+/// each row.
+pub(crate) struct Join<'a> {
+    pub(crate) clause: &'a JoinClause<'a>,
+    pub(crate) source: Option<Object>,
+    pub(crate) items: Vec<Object>,
+    pub(crate) matched: Vec<bool>,
+    // The probe and build sides of the equality
+    key: Option<(&'a Expr<'a>, &'a Expr<'a>)>,
+    index: Option<ObjectMap<Vec<usize>>>,
+}
+
+impl<'a> Join<'a> {
+    /// Creates a join where `bound` are the names bound before it.
+    pub(crate) fn new(
+        clause: &'a JoinClause<'a>,
+        source: Option<Object>,
+        bound: &[&str],
+    ) -> Result<Self> {
+        let items: Vec<_> = match source.as_ref() {
+            Some(source) => source.iter()?.collect(),
+            None => Vec::new(),
+        };
+        let key = clause
+            .filter
+            .as_ref()
+            .and_then(|filter| find_key(filter, &clause.bind.names(), bound));
+        Ok(Self {
+            clause,
+            source,
+            matched: vec![false; items.len()],
+            items,
+            key,
+            index: None,
+        })
+    }
+
+    /// Returns the indices of the items that may match the on clause with
+    /// `vars` bound, in order.
+    pub(crate) fn candidates(&mut self, rt: &Runtime, vars: &Vars) -> Vec<usize> {
+        self.lookup(rt, vars)
+            .unwrap_or_else(|| (0..self.items.len()).collect())
+    }
+
+    fn lookup(&mut self, rt: &Runtime, vars: &Vars) -> Option<Vec<usize>> {
+        let (probe, build) = self.key?;
+        if self.index.is_none() {
+            match build_index(rt, &self.clause.bind, build, &self.items) {
+                Ok(index) => self.index = Some(index),
+                // Errors are left to the on clause, which reports them with
+                // the same item order as without an index.
+                Err(_) => {
+                    self.key = None;
+                    return None;
+                }
+            }
+        }
+        let value = rt.enter(vars.clone()).eval(probe).ok()?;
+        match self.index.as_ref()?.get(&value) {
+            Ok(indices) => Some(indices.cloned().unwrap_or_default()),
+            Err(_) => None,
+        }
+    }
+}
+
+fn build_index(
+    rt: &Runtime,
+    bind: &Pattern,
+    build: &Expr,
+    items: &[Object],
+) -> Result<ObjectMap<Vec<usize>>> {
+    let mut index = ObjectMap::new();
+    for (i, item) in items.iter().enumerate() {
+        let scope = rt.enter(rt.bind(bind, item.clone())?);
+        let key = scope.eval(build)?;
+        index.get_or_insert_with(key, Vec::new)?.push(i);
+    }
+    Ok(index)
+}
+
+// Finds an equality in the conjunction `filter` whose one side doesn't refer
+// to `names`, and whose other side doesn't refer to `bound`. Equalities with
+// effects are evaluated for every row, so they aren't indexed.
+fn find_key<'a>(
+    filter: &'a Expr<'a>,
+    names: &[&str],
+    bound: &[&str],
+) -> Option<(&'a Expr<'a>, &'a Expr<'a>)> {
+    match &filter.kind {
+        ExprKind::BoolOp(op, lhs, rhs) if op.kind == BoolOp::And => {
+            find_key(lhs, names, bound).or_else(|| find_key(rhs, names, bound))
+        }
+        ExprKind::CmpOp(op, lhs, rhs) if op.kind == CmpOp::Eq && !has_effects(filter) => {
+            [(lhs, rhs), (rhs, lhs)]
+                .into_iter()
+                .find(|(probe, build)| !refers_to(probe, names) && !refers_to(build, bound))
+                .map(|(probe, build)| (&**probe, &**build))
+        }
+        _ => None,
+    }
+}
+
+// Returns whether `expr` refers to any of `names`.
+pub(crate) fn refers_to(expr: &Expr, names: &[&str]) -> bool {
+    !names.is_empty()
+        && any_expr(expr, &mut |expr| match &expr.kind {
+            ExprKind::Name(ident) => names.contains(&ident.name.as_ref()),
+            _ => false,
+        })
+}
+
+// Returns whether evaluating `expr` may change variables or collections.
+fn has_effects(expr: &Expr) -> bool {
+    any_expr(expr, &mut |expr| match &expr.kind {
+        ExprKind::Func(def) => def.func().name.is_some(),
+        ExprKind::Insert(_)
+        | ExprKind::Update(_)
+        | ExprKind::Delete(_)
+        | ExprKind::Assign(..)
+        | ExprKind::CompoundAssign(..) => true,
+        _ => false,
+    })
+}
+
+// Returns whether `f` is true for `expr` or any expression in it, including
+// the expressions in its clauses, blocks and function bodies.
+fn any_expr(expr: &Expr, f: &mut dyn FnMut(&Expr) -> bool) -> bool {
+    if f(expr) {
+        return true;
+    }
+    let mut exprs: Vec<&Expr> = Vec::new();
+    match &expr.kind {
+        ExprKind::Lit(_) | ExprKind::Name(_) => {}
+        ExprKind::Format(parts) => exprs.extend(parts.iter().filter_map(|part| match part {
+            FormatPart::Str(_) => None,
+            FormatPart::Expr(expr) => Some(expr),
+        })),
+        ExprKind::List(items) => exprs.extend(items),
+        ExprKind::Hash(pairs) => exprs.extend(pairs.iter().map(|(_, value)| value)),
+        ExprKind::Call(func, args) => exprs.extend([&**func].into_iter().chain(args)),
+        ExprKind::Field(expr, _) | ExprKind::SafeField(expr, _) | ExprKind::UnOp(_, expr) => {
+            exprs.push(expr)
+        }
+        ExprKind::Range(range) => exprs.extend(range.start.iter().chain(&range.end)),
+        ExprKind::Index(lhs, rhs)
+        | ExprKind::BinOp(_, lhs, rhs)
+        | ExprKind::CmpOp(_, lhs, rhs)
+        | ExprKind::BoolOp(_, lhs, rhs)
+        | ExprKind::Coalesce(lhs, rhs)
+        | ExprKind::SetOp(_, lhs, rhs)
+        | ExprKind::Assign(lhs, rhs)
+        | ExprKind::CompoundAssign(_, lhs, rhs) => exprs.extend([&**lhs, &**rhs]),
+        ExprKind::Insert(insert) => {
+            exprs.push(&insert.into);
+            exprs.extend(&insert.values);
+            exprs.extend(insert.returning.iter().map(|(_, expr)| expr));
+        }
+        ExprKind::Update(update) => {
+            from_exprs(&update.from, &mut exprs);
+            exprs.extend(&update.exprs);
+            exprs.extend(&update.returning);
+        }
+        ExprKind::Delete(delete) => {
+            from_exprs(&delete.from, &mut exprs);
+            exprs.push(&delete.target);
+            exprs.extend(&delete.returning);
+        }
+        ExprKind::Select(select) => {
+            from_exprs(&select.from, &mut exprs);
+            exprs.extend(&select.group_by);
+            exprs.extend(select.order_by.iter().map(|key| &key.expr));
+            exprs.extend(&select.project);
+            exprs.extend(&select.limit);
+            exprs.extend(&select.offset);
+        }
+        ExprKind::Block(block) => return any_stmt(&block.stmts, f),
+        ExprKind::If(if_) => exprs.extend([&if_.cond, &if_.then].into_iter().chain(&if_.else_)),
+        ExprKind::Func(def) => exprs.push(&def.func().body),
+    }
+    exprs.into_iter().any(|expr| any_expr(expr, f))
+}
+
+fn any_stmt(stmts: &[Stmt], f: &mut dyn FnMut(&Expr) -> bool) -> bool {
+    stmts.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Expr(expr) => any_expr(expr, f),
+        StmtKind::Let(let_) => any_expr(&let_.value, f),
+        StmtKind::For(for_) => any_expr(&for_.source, f) || any_stmt(&for_.body.stmts, f),
+        StmtKind::While(while_) => any_expr(&while_.cond, f) || any_stmt(&while_.body.stmts, f),
+        StmtKind::Block(block) => any_stmt(&block.stmts, f),
+        StmtKind::Import(_) | StmtKind::Break | StmtKind::Continue => false,
+    })
+}
+
+fn from_exprs<'e>(from: &'e FromClause, exprs: &mut Vec<&'e Expr<'e>>) {
+    exprs.push(&from.source);
+    for join in &from.joins {
+        exprs.push(&join.source);
+        exprs.extend(&join.filter);
+    }
+    exprs.extend(&from.filter);
+}
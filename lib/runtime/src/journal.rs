@@ -0,0 +1,102 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::{Closure, Object};
+
+/// The writes made while updates and deletes are evaluated, so that they can
+/// be undone if one of them fails.
+///
+/// A write records only the old value of what it changes, and undoing it puts
+/// the old value back into the same scope or object, so objects that are
+/// shared by several variables or collections stay shared.
+#[derive(Default)]
+pub(crate) struct Journal {
+    // The number of updates and deletes being evaluated
+    depth: Cell<usize>,
+    writes: RefCell<Vec<Write>>,
+}
+
+pub(crate) enum Write {
+    // A variable of a scope, and its old value if it was declared
+    Var(Rc<RefCell<Closure>>, String, Option<Object>),
+    // A hash entry, and its old value if it was in the hash
+    Field(Object, String, Option<Object>),
+    // A list item at an index, and its old value
+    Item(Object, Object, Object),
+    // The length of a list before items were appended
+    Append(Object, usize),
+    // The contents of a list before they were replaced
+    List(Object, Vec<Object>),
+    // A hash entry removed at a position
+    RemovedEntry(Object, usize, String, Object),
+    // A list item removed at an index
+    RemovedItem(Object, usize, Object),
+}
+
+impl Journal {
+    /// Evaluates `f`, and undoes the writes it made if it fails.
+    pub(crate) fn atomic<T, E>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let start = self.writes.borrow().len();
+        self.depth.set(self.depth.get() + 1);
+        let result = f();
+        self.depth.set(self.depth.get() - 1);
+        if result.is_err() {
+            let writes = self.writes.borrow_mut().split_off(start);
+            writes.into_iter().rev().for_each(Write::undo);
+        } else if self.depth.get() == 0 {
+            self.writes.borrow_mut().clear();
+        }
+        result
+    }
+
+    /// Records a write, if it may have to be undone.
+    pub(crate) fn record(&self, write: impl FnOnce() -> Write) {
+        if self.depth.get() > 0 {
+            self.writes.borrow_mut().push(write());
+        }
+    }
+}
+
+impl Write {
+    fn undo(self) {
+        match self {
+            Write::Var(scope, name, Some(value)) => {
+                scope.borrow_mut().vars.insert(name, value);
+            }
+            Write::Var(scope, name, None) => {
+                scope.borrow_mut().vars.shift_remove(&name);
+            }
+            Write::Field(mut object, name, value) => {
+                if let Some(hash) = object.as_hash_mut() {
+                    match value {
+                        Some(value) => hash.insert(name, value),
+                        None => hash.shift_remove(&name),
+                    };
+                }
+            }
+            Write::Item(mut object, index, value) => {
+                let _ = object.set_index(&index, value);
+            }
+            Write::Append(mut object, len) => {
+                if let Some(list) = object.as_list_mut() {
+                    list.truncate(len);
+                }
+            }
+            Write::List(mut object, items) => {
+                if let Some(list) = object.as_list_mut() {
+                    *list = items;
+                }
+            }
+            Write::RemovedEntry(mut object, i, name, value) => {
+                if let Some(hash) = object.as_hash_mut() {
+                    hash.shift_insert(i, name, value);
+                }
+            }
+            Write::RemovedItem(mut object, i, value) => {
+                if let Some(list) = object.as_list_mut() {
+                    list.insert(i, value);
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,12 @@
-use std::collections::HashMap;
 use std::fs;
 
+use indexmap::IndexMap;
 use serde_json::Value;
 
 use crate::{Error, Object, Result};
 
 pub(crate) fn module() -> Object {
-    let mut module = HashMap::new();
+    let mut module = IndexMap::new();
     module.insert("load".into(), load.into());
     module.into()
 }
@@ -19,9 +19,9 @@ fn load(_: &Object, args: &[Object]) -> Result<Object> {
         .as_str()
         .ok_or_else(|| Error::new("expect a path argument"))?;
     let text = fs::read_to_string(path)
-        .map_err(|e| Error::new(format!("failed to read '{path}': {}", e.to_string())))?;
+        .map_err(|e| Error::new(format!("failed to read '{path}': {}", e)))?;
     let value = serde_json::from_str(&text)
-        .map_err(|e| Error::new(format!("failed to parse json: {}", e.to_string())))?;
+        .map_err(|e| Error::new(format!("failed to parse json: {}", e)))?;
     value_to_object(value)
 }
 
@@ -47,7 +47,7 @@ fn value_to_object(value: Value) -> Result<Object> {
         Value::Object(o) => o
             .into_iter()
             .map(|(k, v)| value_to_object(v).map(|v| (k, v)))
-            .collect::<Result<HashMap<_, _>>>()
+            .collect::<Result<IndexMap<_, _>>>()
             .map(|x| x.into()),
     }
 }
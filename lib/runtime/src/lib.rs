@@ -1,36 +1,115 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+use indexmap::IndexMap;
 use sigma_parser::ast::*;
 
 mod error;
-pub use error::{Error, Result};
+use error::Flow;
+pub use error::{Error, Label, Result};
 
 mod object;
 pub use object::Object;
 
+mod map;
+use map::ObjectMap;
+
+mod join;
+use join::Join;
+
+mod journal;
+use journal::{Journal, Write};
+
+mod aggregate;
+mod collection;
 mod json;
 
+/// The options of a runtime.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Whether assigning to a name that isn't declared is an error.
+    pub strict: bool,
+    /// Whether a field access is null if the object is null or has no such
+    /// field, like a safe field access.
+    pub lenient: bool,
+}
+
+#[derive(Clone)]
 pub struct Runtime {
     builtin: Rc<Builtin>,
     closure: Rc<RefCell<Closure>>,
+    // The rows of the current group in a group by select, which are kept in
+    // the nested scopes of the select expression
+    group: Option<Rc<[Row]>>,
 }
 
+// The scope and the value of a row in a select
+type Row = (Runtime, Object);
+
 impl Runtime {
-    fn new() -> Self {
+    pub fn new(options: Options) -> Self {
         object::init();
-        let builtin = Rc::new(Builtin::new());
+        let builtin = Rc::new(Builtin::new(options));
         let closure = Rc::new(RefCell::default());
-        Self { builtin, closure }
+        Self {
+            builtin,
+            closure,
+            group: None,
+        }
     }
 
     fn var(&self, name: &str) -> Option<Object> {
         self.closure.borrow().var(name)
     }
 
-    fn set_var(&self, name: impl ToString, value: Object) {
-        self.closure.borrow_mut().set_var(name.to_string(), value);
+    fn declare_var(&self, name: impl ToString, value: Object) {
+        self.closure
+            .borrow_mut()
+            .declare_var(name.to_string(), value);
+    }
+
+    // Updates the name in the scope that has it. If no scope has it, the name
+    // is declared in the current scope, or it's an error in strict mode.
+    fn set_var(&self, name: &str, value: Object) -> Result<()> {
+        let scope = match Closure::scope_of(&self.closure, name) {
+            Some(scope) => scope,
+            None if self.builtin.options.strict => {
+                return Err(Error::new(format!("name '{name}' is not declared")));
+            }
+            None => self.closure.clone(),
+        };
+        let old_value = scope.borrow_mut().vars.insert(name.to_owned(), value);
+        self.builtin
+            .journal
+            .record(|| Write::Var(scope, name.to_owned(), old_value));
+        Ok(())
+    }
+
+    fn set_field(&self, this: &mut Object, name: &str, value: Object) -> Result<()> {
+        let old_value = this.as_hash().and_then(|hash| hash.get(name).cloned());
+        this.set_field(name, value)?;
+        self.builtin
+            .journal
+            .record(|| Write::Field(this.clone(), name.to_owned(), old_value));
+        Ok(())
+    }
+
+    fn set_index(&self, this: &mut Object, index: &Object, value: Object) -> Result<()> {
+        if this.as_hash().is_some() {
+            if let Some(name) = index.as_str() {
+                return self.set_field(this, name, value);
+            }
+        }
+        let old_value = this.index(index);
+        this.set_index(index, value)?;
+        if let Ok(old_value) = old_value {
+            self.builtin
+                .journal
+                .record(|| Write::Item(this.clone(), index.clone(), old_value));
+        }
+        Ok(())
     }
 
     fn enter(&self, vars: Vars) -> Self {
@@ -40,6 +119,14 @@ impl Runtime {
                 vars,
                 outer: Some(self.closure.clone()),
             })),
+            group: self.group.clone(),
+        }
+    }
+
+    fn enter_group(&self, vars: Vars, rows: Vec<Row>) -> Self {
+        Self {
+            group: Some(rows.into()),
+            ..self.enter(vars)
         }
     }
 }
@@ -49,12 +136,18 @@ impl Runtime {
         match &stmt.kind {
             StmtKind::Expr(expr) => self.eval(expr).map(Some),
             StmtKind::Import(name) => self.exec_import(name).map(|_| None),
+            StmtKind::Let(let_) => self.exec_let(let_).map(|_| None),
+            StmtKind::For(for_) => self.exec_for(for_).map(|_| None),
+            StmtKind::While(while_) => self.exec_while(while_).map(|_| None),
+            StmtKind::Block(block) => self.exec_block(block, Vars::new()),
+            StmtKind::Break => Err(Error::flow(stmt.span.clone(), Flow::Break)),
+            StmtKind::Continue => Err(Error::flow(stmt.span.clone(), Flow::Continue)),
         }
     }
 
     fn exec_import(&self, ident: &Ident) -> Result<()> {
-        if let Some(module) = self.builtin.modules.get(ident.name).cloned() {
-            self.set_var(ident.name, module);
+        if let Some(module) = self.builtin.modules.get(ident.name.as_ref()).cloned() {
+            self.declare_var(&ident.name, module);
             Ok(())
         } else {
             Err(Error::with_span(
@@ -64,23 +157,82 @@ impl Runtime {
         }
     }
 
+    fn exec_let(&self, let_: &Let) -> Result<()> {
+        let value = self.eval(&let_.value)?;
+        for (name, value) in self.bind(&let_.bind, value)? {
+            self.declare_var(name, value);
+        }
+        Ok(())
+    }
+
+    fn exec_for(&self, for_: &For) -> Result<()> {
+        for item in self.eval(&for_.source)?.iter()? {
+            let vars = self.bind(&for_.bind, item)?;
+            if !self.exec_loop_body(&for_.body, vars)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_while(&self, while_: &While) -> Result<()> {
+        while self.eval_bool(&while_.cond, "while condition")? {
+            if !self.exec_loop_body(&while_.body, Vars::new())? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Returns false if the loop is broken.
+    fn exec_loop_body(&self, body: &Block, vars: Vars) -> Result<bool> {
+        match self.exec_block(body, vars) {
+            Ok(_) => Ok(true),
+            Err(e) => match e.flow {
+                Some(Flow::Break) => Ok(false),
+                Some(Flow::Continue) => Ok(true),
+                None => Err(e),
+            },
+        }
+    }
+
+    // Returns the value of the last statement.
+    fn exec_block(&self, block: &Block, vars: Vars) -> Result<Option<Object>> {
+        let scope = self.enter(vars);
+        let mut value = None;
+        for stmt in &block.stmts {
+            value = scope.exec(stmt)?;
+        }
+        Ok(value)
+    }
+
     fn eval(&self, expr: &Expr) -> Result<Object> {
         match &expr.kind {
             ExprKind::Lit(lit) => self.eval_lit(lit),
             ExprKind::Name(name) => self.eval_name(name),
+            ExprKind::Format(parts) => self.eval_format(parts),
             ExprKind::List(list) => self.eval_list(list),
             ExprKind::Hash(hash) => self.eval_hash(hash),
-            ExprKind::Call(expr, args) => self.eval_call(expr, args),
-            ExprKind::Index(expr, index) => self.eval_index(expr, index),
-            ExprKind::Field(expr, field) => self.eval_field(expr, field),
+            ExprKind::Call(..)
+            | ExprKind::Index(..)
+            | ExprKind::Field(..)
+            | ExprKind::SafeField(..) => self
+                .eval_postfix(expr)
+                .map(|value| value.unwrap_or_else(|| self.builtin.null.clone())),
+            ExprKind::Range(range) => self.eval_range(range),
             ExprKind::UnOp(op, expr) => self.eval_unop(op, expr),
             ExprKind::BinOp(op, lhs, rhs) => self.eval_binop(op, lhs, rhs),
             ExprKind::CmpOp(op, lhs, rhs) => self.eval_cmpop(op, lhs, rhs),
             ExprKind::BoolOp(op, lhs, rhs) => self.eval_boolop(op, lhs, rhs),
+            ExprKind::Coalesce(lhs, rhs) => self.eval_coalesce(lhs, rhs),
             ExprKind::Insert(insert) => self.eval_insert(insert),
             ExprKind::Update(update) => self.eval_update(update),
             ExprKind::Delete(delete) => self.eval_delete(delete),
             ExprKind::Select(select) => self.eval_select(select),
+            ExprKind::SetOp(op, lhs, rhs) => self.eval_setop(op, lhs, rhs),
+            ExprKind::Block(block) => self.eval_block(block),
+            ExprKind::If(if_) => self.eval_if(if_),
+            ExprKind::Func(func) => self.eval_func(func),
             ExprKind::Assign(lhs, rhs) => self.eval_assign(lhs, rhs),
             ExprKind::CompoundAssign(op, lhs, rhs) => self.eval_compound_assign(op, lhs, rhs),
         }
@@ -93,12 +245,12 @@ impl Runtime {
     }
 
     fn eval_lit(&self, lit: &Lit) -> Result<Object> {
-        match lit.kind {
+        match &lit.kind {
             LitKind::Null => Ok(self.builtin.null.clone()),
             LitKind::Bool(true) => Ok(self.builtin.true_.clone()),
             LitKind::Bool(false) => Ok(self.builtin.false_.clone()),
-            LitKind::Str(s) => Ok(s.into()),
-            LitKind::Int(s, radix) => i64::from_str_radix(s, radix as u32)
+            LitKind::Str(s) => Ok(s.as_ref().into()),
+            LitKind::Int(s, radix) => i64::from_str_radix(s, *radix as u32)
                 .map(Into::into)
                 .map_err(Error::new),
             LitKind::Float(s) => s.parse::<f64>().map(Into::into).map_err(Error::new),
@@ -106,7 +258,11 @@ impl Runtime {
     }
 
     fn eval_name(&self, ident: &Ident) -> Result<Object> {
-        self.var(ident.name).ok_or_else(|| {
+        let value = self
+            .var(&ident.name)
+            .or_else(|| self.builtin.functions.get(ident.name.as_ref()).cloned())
+            .or_else(|| self.builtin.aggregates.get(ident.name.as_ref()).cloned());
+        value.ok_or_else(|| {
             Error::with_span(
                 ident.span.clone(),
                 format!("name '{}' is not defined", ident.name),
@@ -114,6 +270,23 @@ impl Runtime {
         })
     }
 
+    fn eval_format(&self, parts: &[FormatPart]) -> Result<Object> {
+        let mut output = String::new();
+        for part in parts {
+            match part {
+                FormatPart::Str(s) => output.push_str(s),
+                FormatPart::Expr(expr) => {
+                    let value = self.eval(expr)?;
+                    match value.as_str() {
+                        Some(s) => output.push_str(s),
+                        None => output.push_str(&value.to_string()),
+                    }
+                }
+            }
+        }
+        Ok(output.into())
+    }
+
     fn eval_list(&self, list: &[Expr]) -> Result<Object> {
         list.iter()
             .map(|expr| self.eval(expr))
@@ -123,29 +296,123 @@ impl Runtime {
 
     fn eval_hash(&self, hash: &[(Field, Expr)]) -> Result<Object> {
         hash.iter()
-            .map(|(field, expr)| self.eval(expr).map(|value| (field.name.to_owned(), value)))
+            .map(|(field, expr)| self.eval(expr).map(|value| (field.name.to_string(), value)))
             .collect::<Result<Vec<_>>>()
             .map(|hash| hash.into())
     }
 
-    fn eval_call(&self, expr: &Expr, args: &[Expr]) -> Result<Object> {
-        let this = self.eval(expr)?;
+    // Evaluates a call, an index or a field access, or returns none if a
+    // '?.' in the chain of them was applied to null, which skips the rest of
+    // the chain.
+    fn eval_postfix(&self, expr: &Expr) -> Result<Option<Object>> {
+        match &expr.kind {
+            ExprKind::Call(expr, args) => self.eval_call(expr, args),
+            ExprKind::Index(expr, index) => self.eval_index(expr, index),
+            ExprKind::Field(expr, field) => self.eval_field(expr, field),
+            ExprKind::SafeField(expr, field) => self.eval_safe_field(expr, field),
+            _ => self.eval(expr).map(Some),
+        }
+    }
+
+    fn eval_call(&self, expr: &Expr, args: &[Expr]) -> Result<Option<Object>> {
+        if let (Some(rows), ExprKind::Name(ident)) = (self.group.as_ref(), &expr.kind) {
+            if self.var(&ident.name).is_none() {
+                if let Some(func) = self.builtin.aggregates.get(ident.name.as_ref()) {
+                    return self.eval_aggregate(rows, func, args).map(Some);
+                }
+            }
+        }
+        let Some(this) = self.eval_postfix(expr)? else {
+            return Ok(None);
+        };
         let args = args
             .iter()
             .map(|expr| self.eval(expr))
             .collect::<Result<Vec<_>>>()?;
-        this.call(&args)
+        let value = this.call(&args).map_err(|e| {
+            // An error in a function body is labeled with the call.
+            if e.source.is_some() {
+                e.with_label(expr.span.clone(), "called here")
+            } else {
+                e
+            }
+        })?;
+        Ok(Some(value))
     }
 
-    fn eval_index(&self, expr: &Expr, index: &Expr) -> Result<Object> {
-        let this = self.eval(expr)?;
+    // Calls an aggregate function with the values of the argument over the
+    // rows of a group, or the rows themselves if there is no argument.
+    fn eval_aggregate(&self, rows: &[Row], func: &Object, args: &[Expr]) -> Result<Object> {
+        let values = match args {
+            [] => rows.iter().map(|(_, row)| Ok(row.clone())).collect(),
+            [arg] => rows.iter().map(|(scope, _)| scope.eval(arg)).collect(),
+            _ => Err(Error::new("aggregate function expects at most 1 argument")),
+        };
+        func.call(&[values.map(Vec::into)?])
+    }
+
+    fn eval_index(&self, expr: &Expr, index: &Expr) -> Result<Option<Object>> {
+        let Some(this) = self.eval_postfix(expr)? else {
+            return Ok(None);
+        };
         let value = self.eval(index)?;
-        this.index(&value)
+        this.index(&value).map(Some)
     }
 
-    fn eval_field(&self, expr: &Expr, field: &Field) -> Result<Object> {
-        let this = self.eval(expr)?;
-        this.field(field.name)
+    fn eval_field(&self, expr: &Expr, field: &Field) -> Result<Option<Object>> {
+        let Some(this) = self.eval_postfix(expr)? else {
+            return Ok(None);
+        };
+        self.field(&this, field).map(Some)
+    }
+
+    fn eval_safe_field(&self, expr: &Expr, field: &Field) -> Result<Option<Object>> {
+        match self.eval_postfix(expr)? {
+            Some(this) if !this.is_null() => self.safe_field(&this, field).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // Reads a field, which is null if it's missing or of null in lenient mode.
+    fn field(&self, this: &Object, field: &Field) -> Result<Object> {
+        if self.builtin.options.lenient {
+            self.safe_field(this, field)
+        } else {
+            this.field(&field.name)
+        }
+    }
+
+    fn safe_field(&self, this: &Object, field: &Field) -> Result<Object> {
+        if let Some(hash) = this.as_hash() {
+            let value = hash.get(field.name.as_ref()).cloned();
+            Ok(value.unwrap_or_else(|| self.builtin.null.clone()))
+        } else if this.is_null() {
+            Ok(this.clone())
+        } else {
+            this.field(&field.name)
+        }
+    }
+
+    fn eval_range(&self, range: &Range) -> Result<Object> {
+        let bound = |expr: &Option<Expr>| {
+            expr.as_ref()
+                .map(|expr| {
+                    let value = self.eval(expr)?;
+                    value.as_i64().ok_or_else(|| {
+                        Error::with_span(
+                            expr.span.clone(),
+                            format!("range bound must be 'i64', not '{}'", value.type_name()),
+                        )
+                    })
+                })
+                .transpose()
+        };
+        Ok(object::Range {
+            start: bound(&range.start)?,
+            end: bound(&range.end)?,
+            inclusive: range.inclusive,
+        }
+        .into())
     }
 
     fn eval_unop(&self, op: &Spanned<UnOp>, expr: &Expr) -> Result<Object> {
@@ -193,141 +460,864 @@ impl Runtime {
         Ok(other)
     }
 
+    fn eval_coalesce(&self, lhs: &Expr, rhs: &Expr) -> Result<Object> {
+        let this = self.eval(lhs)?;
+        if this.is_null() {
+            self.eval(rhs)
+        } else {
+            Ok(this)
+        }
+    }
+
     fn eval_insert(&self, insert: &Insert) -> Result<Object> {
         let mut this = self.eval(&insert.into)?;
-        for expr in &insert.values {
-            let value = self.eval(expr)?;
+        let values = insert
+            .values
+            .iter()
+            .map(|expr| self.eval(expr))
+            .collect::<Result<Vec<_>>>()?;
+        // The returning clause is evaluated before anything is inserted, so
+        // nothing is inserted if it fails.
+        let returned = match insert.returning.as_ref() {
+            Some((bind, returning)) => Some(
+                values
+                    .iter()
+                    .map(|value| self.enter(self.bind(bind, value.clone())?).eval(returning))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+        let count = values.len() as i64;
+        if let Some(list) = this.as_list() {
+            let len = list.len();
+            self.builtin
+                .journal
+                .record(|| Write::Append(this.clone(), len));
+        }
+        for value in values {
             this.insert(value)?;
         }
-        Ok((insert.values.len() as i64).into())
+        match returned {
+            Some(returned) => Ok(returned.into()),
+            None => Ok(count.into()),
+        }
     }
 
     fn eval_update(&self, update: &Update) -> Result<Object> {
-        let mut count = 0;
         let from = &update.from;
-        let name = from.bind.name;
         let mut source = self.eval(&from.source)?;
-        for item in source.iter_mut()? {
-            let inner = self.enter([(name.to_owned(), item.clone())].into());
-            if let Some(filter) = from.filter.as_ref() {
-                if !inner.eval_filter(filter)? {
-                    continue;
+        check_joins(from, "update", &[JoinKind::Full, JoinKind::Lateral])?;
+        let mut joins = self.eval_join_sources(from)?;
+        // The new items are written after every row succeeds. If a row fails,
+        // the writes of the update expressions are undone.
+        self.builtin
+            .journal
+            .atomic(|| self.update_items(update, &mut source, &mut joins))
+    }
+
+    fn update_items(
+        &self,
+        update: &Update,
+        source: &mut Object,
+        joins: &mut [Join],
+    ) -> Result<Object> {
+        let mut count = 0;
+        let from = &update.from;
+        let items: Vec<_> = source.iter()?.collect();
+        // The keys of hash entries, in the order of the items
+        let keys: Option<Vec<String>> = source.as_hash().map(|hash| hash.keys().cloned().collect());
+        let mut updates = Vec::new();
+        let mut returned = Vec::new();
+        for item in &items {
+            // Each item is updated at most once, with its first matching row.
+            let mut updated = None;
+            let vars = self.bind(&from.bind, item.clone())?;
+            self.eval_joins(
+                from,
+                joins,
+                vars,
+                &[],
+                &mut Vec::new(),
+                &mut |inner, _, _| {
+                    for expr in &update.exprs {
+                        inner.eval(expr)?;
+                    }
+                    if let Some(returning) = update.returning.as_ref() {
+                        returned.push(inner.eval(returning)?);
+                    }
+                    updated = Some(inner.unbind(&from.bind, item.clone())?);
+                    Ok(false)
+                },
+            )?;
+            if updated.is_some() {
+                count += 1;
+            }
+            updates.push(updated);
+        }
+        // The values of hash entries are updated in place, after every entry
+        // is checked.
+        if let Some(keys) = keys {
+            let mut values = Vec::new();
+            for (key, updated) in keys.into_iter().zip(updates) {
+                if let Some(updated) = updated {
+                    let value = entry_value(&key, &updated).ok_or_else(|| {
+                        Error::with_span(
+                            from.bind.span.clone(),
+                            format!("expect an entry with key '{key}', not '{updated}'"),
+                        )
+                    })?;
+                    values.push((key, value));
                 }
             }
-            for expr in &update.exprs {
-                inner.eval(expr)?;
+            for (key, value) in values {
+                self.set_field(source, &key, value)?;
+            }
+        } else {
+            let list = source.clone();
+            for (i, (item, updated)) in source.iter_mut()?.zip(updates).enumerate() {
+                if let Some(updated) = updated {
+                    let old_item = std::mem::replace(item, updated);
+                    self.builtin
+                        .journal
+                        .record(|| Write::Item(list.clone(), (i as i64).into(), old_item));
+                }
             }
-            *item = inner.var(name).unwrap();
-            count += 1;
+        }
+        if update.returning.is_some() {
+            return Ok(returned.into());
         }
         Ok(count.into())
     }
 
     fn eval_delete(&self, delete: &Delete) -> Result<Object> {
-        let mut count = 0;
         let from = &delete.from;
-        let name = from.bind.name;
-        if delete.target.name != name {
+        let target = match &delete.target.kind {
+            ExprKind::Name(ident) if binds_item(&from.bind, ident) => DeleteTarget::From,
+            ExprKind::Name(ident) => {
+                match from
+                    .joins
+                    .iter()
+                    .position(|join| binds_item(&join.bind, ident))
+                {
+                    Some(i) if from.joins[i].kind == JoinKind::Lateral => {
+                        return Err(Error::with_span(
+                            delete.target.span.clone(),
+                            "cannot delete the items of a lateral source",
+                        ));
+                    }
+                    Some(i) => DeleteTarget::Join(i),
+                    None => return Err(invalid_delete_target(&delete.target)),
+                }
+            }
+            ExprKind::Index(collection, key) => DeleteTarget::Index(collection, key),
+            ExprKind::Field(hash, field) => DeleteTarget::Field(hash, &field.name),
+            _ => return Err(invalid_delete_target(&delete.target)),
+        };
+        // Items are removed from the evaluated source, so deleting from a
+        // temporary collection would change nothing.
+        let owner = match target {
+            DeleteTarget::From => Some(&from.source),
+            DeleteTarget::Join(i) => Some(&from.joins[i].source),
+            _ => None,
+        };
+        if let Some(owner) = owner.filter(|owner| !is_place(owner)) {
             return Err(Error::with_span(
-                delete.target.span.clone(),
-                "delete target should be the same as the bounded name",
+                owner.span.clone(),
+                "delete source should be a variable, or a field or an index of one",
             ));
         }
         let mut source = self.eval(&from.source)?;
-        let mut new_from_source = Vec::new();
-        for item in source.iter()? {
-            let inner = self.enter([(name.to_owned(), item.clone())].into());
-            let delete = if let Some(filter) = from.filter.as_ref() {
-                inner.eval_filter(filter)?
+        check_joins(from, "delete", &[JoinKind::Full])?;
+        let mut joins = self.eval_join_sources(from)?;
+        // Nothing is deleted until every row succeeds. If a row fails, the
+        // writes of the returning clause are undone.
+        self.builtin
+            .journal
+            .atomic(|| self.delete_items(delete, target, &mut source, &mut joins))
+    }
+
+    fn delete_items(
+        &self,
+        delete: &Delete,
+        target: DeleteTarget,
+        source: &mut Object,
+        joins: &mut [Join],
+    ) -> Result<Object> {
+        let from = &delete.from;
+        let items: Vec<_> = source.iter()?.collect();
+        let mut deleted = match target {
+            DeleteTarget::From => vec![false; items.len()],
+            DeleteTarget::Join(i) => vec![false; joins[i].items.len()],
+            _ => Vec::new(),
+        };
+        let mut entries = Vec::new();
+        let mut returned = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let vars = self.bind(&from.bind, item.clone())?;
+            self.eval_joins(
+                from,
+                joins,
+                vars,
+                &[],
+                &mut Vec::new(),
+                &mut |inner, _, indices| {
+                    match target {
+                        DeleteTarget::From => deleted[i] = true,
+                        DeleteTarget::Join(k) => match indices[k] {
+                            Some(j) if !deleted[j] => deleted[j] = true,
+                            _ => return Ok(true),
+                        },
+                        DeleteTarget::Index(collection, key) => {
+                            entries.push(inner.eval_entry(collection, key)?);
+                        }
+                        DeleteTarget::Field(hash, field) => {
+                            let hash = inner.eval_hash_target(hash)?;
+                            entries.push((hash, EntryKey::Field(field.to_owned())));
+                        }
+                    }
+                    if let Some(returning) = delete.returning.as_ref() {
+                        returned.push(inner.eval(returning)?);
+                    }
+                    // An item or a joined item is deleted once, and an entry is
+                    // deleted for every row.
+                    Ok(!matches!(target, DeleteTarget::From))
+                },
+            )?;
+        }
+        let count = match target {
+            DeleteTarget::From => self.remove_items(source, &items, &deleted)?,
+            DeleteTarget::Join(i) => {
+                let join = &mut joins[i];
+                let source = join.source.as_mut().unwrap();
+                self.remove_items(source, &join.items, &deleted)?
+            }
+            DeleteTarget::Index(..) | DeleteTarget::Field(..) => self.remove_entries(entries),
+        };
+        if delete.returning.is_some() {
+            return Ok(returned.into());
+        }
+        Ok(count.into())
+    }
+
+    // Removes the deleted items from a list, or the deleted entries from a
+    // hash, and returns the number of removed items.
+    fn remove_items(&self, source: &mut Object, items: &[Object], deleted: &[bool]) -> Result<i64> {
+        let mut count = 0;
+        let mut kept = Vec::new();
+        for (item, &deleted) in items.iter().zip(deleted) {
+            if deleted {
+                count += 1;
             } else {
+                kept.push(item.clone());
+            }
+        }
+        if count == 0 {
+            return Ok(0);
+        }
+        if source.as_hash().is_some() {
+            for (item, _) in items.iter().zip(deleted).filter(|(_, &deleted)| deleted) {
+                self.remove_entry(source, item.field("key")?.as_str().unwrap());
+            }
+        } else {
+            let old_items = source.as_list().map(|list| list.to_vec());
+            source.replace(kept.into())?;
+            if let Some(old_items) = old_items {
+                self.builtin
+                    .journal
+                    .record(|| Write::List(source.clone(), old_items));
+            }
+        }
+        Ok(count)
+    }
+
+    // Removes the entries from their hashes and lists, and returns the number
+    // of removed entries. An entry deleted by several rows is removed once.
+    fn remove_entries(&self, entries: Vec<(Object, EntryKey)>) -> i64 {
+        let mut count = 0;
+        let mut lists: Vec<(Object, Vec<usize>)> = Vec::new();
+        for (mut collection, key) in entries {
+            match key {
+                EntryKey::Field(field) => {
+                    if self.remove_entry(&mut collection, &field) {
+                        count += 1;
+                    }
+                }
+                EntryKey::Index(i) => match lists
+                    .iter_mut()
+                    .find(|(list, _)| list.id() == collection.id())
+                {
+                    Some((_, indices)) => indices.push(i),
+                    None => lists.push((collection, vec![i])),
+                },
+            }
+        }
+        // Items are removed from the end, so the indices of the others stay
+        // valid.
+        for (mut object, mut indices) in lists {
+            indices.sort_unstable();
+            indices.dedup();
+            let this = object.clone();
+            if let Some(list) = object.as_list_mut() {
+                for i in indices.into_iter().rev() {
+                    let item = list.remove(i);
+                    self.builtin
+                        .journal
+                        .record(|| Write::RemovedItem(this.clone(), i, item));
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    // Removes an entry from a hash, and returns whether it was in the hash.
+    fn remove_entry(&self, this: &mut Object, key: &str) -> bool {
+        let removed = this
+            .as_hash_mut()
+            .and_then(|hash| hash.shift_remove_full(key));
+        match removed {
+            Some((i, key, value)) => {
+                self.builtin
+                    .journal
+                    .record(|| Write::RemovedEntry(this.clone(), i, key, value));
                 true
+            }
+            None => false,
+        }
+    }
+
+    // Evaluates the hash or the list of an entry to delete, and its key.
+    fn eval_entry(&self, collection: &Expr, key: &Expr) -> Result<(Object, EntryKey)> {
+        let this = self.eval(collection)?;
+        let value = self.eval(key)?;
+        if this.as_hash().is_some() {
+            return match value.as_str() {
+                Some(field) => Ok((this, EntryKey::Field(field.to_owned()))),
+                None => Err(Error::with_span(
+                    key.span.clone(),
+                    format!("expect a 'str' key, not '{}'", value.type_name()),
+                )),
             };
-            if !delete {
-                new_from_source.push(item.clone());
+        }
+        let Some(list) = this.as_list() else {
+            return Err(Error::with_span(
+                collection.span.clone(),
+                format!("expect a 'hash' or a 'list', not '{}'", this.type_name()),
+            ));
+        };
+        let len = list.len() as i64;
+        match value.as_i64() {
+            Some(i) if (0..len).contains(&i) => Ok((this, EntryKey::Index(i as usize))),
+            Some(i) if (-len..0).contains(&i) => Ok((this, EntryKey::Index((len + i) as usize))),
+            _ => Err(Error::with_span(
+                key.span.clone(),
+                format!("expect an index of the list, not '{value}'"),
+            )),
+        }
+    }
+
+    // Evaluates the hash of an entry to delete.
+    fn eval_hash_target(&self, expr: &Expr) -> Result<Object> {
+        let value = self.eval(expr)?;
+        if value.as_hash().is_none() {
+            return Err(Error::with_span(
+                expr.span.clone(),
+                format!("expect a 'hash', not '{}'", value.type_name()),
+            ));
+        }
+        Ok(value)
+    }
+
+    fn eval_select(&self, select: &Select) -> Result<Object> {
+        let mut output = Output {
+            items: Vec::new(),
+            seen: select.distinct.then(ObjectMap::new),
+            limit: self.eval_count(select.limit.as_ref())?,
+            offset: self.eval_count(select.offset.as_ref())?.unwrap_or(0),
+        };
+        if output.limit == Some(0) {
+            return Ok(output.items.into());
+        }
+        if let Some(group_by) = select.group_by.as_ref() {
+            let rows = self.eval_groups(&select.from, group_by)?;
+            return self.eval_rows(select, rows, output);
+        }
+        if !select.order_by.is_empty() {
+            let mut rows = Vec::new();
+            self.eval_from(&select.from, |inner, row| {
+                rows.push((inner.clone(), row));
+                Ok(true)
+            })?;
+            return self.eval_rows(select, rows, output);
+        }
+        self.eval_from(&select.from, |inner, row| {
+            if output.skip() {
+                return Ok(true);
+            }
+            output.push(inner.eval_project(select, row)?)
+        })?;
+        Ok(output.items.into())
+    }
+
+    // Groups the rows of the from clause by key.
+    //
+    // Returns a row for each group, whose scope binds the group key to `key`
+    // and whose value is a hash of the key and the items of the group.
+    fn eval_groups(&self, from: &FromClause, group_by: &Expr) -> Result<Vec<Row>> {
+        let mut groups = ObjectMap::new();
+        self.eval_from(from, |inner, row| {
+            let key = inner.eval(group_by)?;
+            groups
+                .get_or_insert_with(key, Vec::new)
+                .map_err(|e| Error::with_span(group_by.span.clone(), e.message))?
+                .push((inner.clone(), row));
+            Ok(true)
+        })?;
+        Ok(groups
+            .into_iter()
+            .map(|(key, rows)| {
+                let items = rows.iter().map(|(_, row)| row.clone()).collect::<Vec<_>>();
+                let row =
+                    Vars::from_iter([("key".into(), key.clone()), ("items".into(), items.into())]);
+                let scope = self.enter_group([("key".into(), key)].into(), rows);
+                (scope, row.into())
+            })
+            .collect())
+    }
+
+    // Sorts and projects rows that have been collected.
+    fn eval_rows(&self, select: &Select, rows: Vec<Row>, mut output: Output) -> Result<Object> {
+        let mut rows = rows
+            .into_iter()
+            .map(|(scope, row)| {
+                let keys = select
+                    .order_by
+                    .iter()
+                    .map(|key| scope.eval(&key.expr))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((keys, scope, row))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut error = None;
+        rows.sort_by(|(a, ..), (b, ..)| {
+            compare_sort_keys(&select.order_by, a, b).unwrap_or_else(|e| {
+                error.get_or_insert(e);
+                Ordering::Equal
+            })
+        });
+        if let Some(e) = error {
+            return Err(e);
+        }
+        for (_, scope, row) in rows {
+            if output.skip() {
                 continue;
             }
-            count += 1;
+            if !output.push(scope.eval_project(select, row)?)? {
+                break;
+            }
         }
-        source.replace(new_from_source.into())?;
-        Ok(count.into())
+        Ok(output.items.into())
     }
 
-    fn eval_select(&self, select: &Select) -> Result<Object> {
-        let mut output = Vec::new();
-        let from = &select.from;
-        let from_name = from.bind.name;
+    fn eval_project(&self, select: &Select, row: Object) -> Result<Object> {
+        match select.project.as_ref() {
+            Some(project) => self.eval(project),
+            None => Ok(row),
+        }
+    }
+
+    // Calls `f` with the scope and the row of each item that matches the from
+    // clause, until `f` returns false.
+    //
+    // The row is the item itself, or a hash of the bound names if there are
+    // join clauses.
+    fn eval_from(
+        &self,
+        from: &FromClause,
+        mut f: impl FnMut(&Runtime, Object) -> Result<bool>,
+    ) -> Result<()> {
         let from_source = self.eval(&from.source)?;
-        for from_item in from_source.iter()? {
-            if let Some(join) = from.join.as_ref() {
-                let join_name = join.bind.name;
-                let join_source = self.eval(&join.source)?;
-                for join_item in join_source.iter()? {
-                    let vars = Vars::from_iter([
-                        (from_name.to_owned(), from_item.clone()),
-                        (join_name.to_owned(), join_item.clone()),
-                    ]);
-                    let inner = self.enter(vars.clone());
-                    if let Some(filter) = join.filter.as_ref() {
-                        if !inner.eval_filter(filter)? {
-                            continue;
-                        }
-                    }
-                    if let Some(filter) = from.filter.as_ref() {
-                        if !inner.eval_filter(filter)? {
-                            continue;
-                        }
-                    }
-                    let item = if let Some(project) = select.project.as_ref() {
-                        inner.eval(project)?
-                    } else {
-                        vars.into()
-                    };
-                    output.push(item);
-                }
-            } else {
-                let vars = Vars::from_iter([(from_name.to_owned(), from_item.clone())]);
+        if from.joins.is_empty() {
+            for from_item in from_source.iter()? {
+                let vars = self.bind(&from.bind, from_item.clone())?;
                 let inner = self.enter(vars);
                 if let Some(filter) = from.filter.as_ref() {
                     if !inner.eval_filter(filter)? {
                         continue;
                     }
                 }
-                let item = if let Some(project) = select.project.as_ref() {
-                    inner.eval(project)?
-                } else {
-                    from_item.clone()
+                if !f(&inner, from_item)? {
+                    return Ok(());
+                }
+            }
+            return Ok(());
+        }
+
+        let mut joins = self.eval_join_sources(from)?;
+        let mut f = |inner: &Runtime, row, _: &[Option<usize>]| f(inner, row);
+        for from_item in from_source.iter()? {
+            let vars = self.bind(&from.bind, from_item)?;
+            if !self.eval_joins(from, &mut joins, vars, &[], &mut Vec::new(), &mut f)? {
+                return Ok(());
+            }
+        }
+        for i in 0..joins.len() {
+            if joins[i].clause.kind != JoinKind::Full {
+                continue;
+            }
+            let (left, right) = joins.split_at_mut(i + 1);
+            let join = &left[i];
+            let unmatched = join
+                .items
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| !join.matched[j]);
+            let mut padded = from.bind.names();
+            for join in &left[..i] {
+                padded.extend(join.clause.bind.names());
+            }
+            for (j, item) in unmatched {
+                let mut vars = Vars::new();
+                for name in &padded {
+                    vars.insert((*name).to_owned(), self.builtin.null.clone());
+                }
+                vars.extend(self.bind(&join.clause.bind, item.clone())?);
+                let mut indices = vec![None; i];
+                indices.push(Some(j));
+                if !self.eval_joins(from, right, vars, &padded, &mut indices, &mut f)? {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Binds the names of a pattern to the parts of an item.
+    fn bind(&self, pattern: &Pattern, item: Object) -> Result<Vars> {
+        let mut vars = Vars::new();
+        self.bind_into(pattern, item, &mut vars)?;
+        Ok(vars)
+    }
+
+    fn bind_into(&self, pattern: &Pattern, item: Object, vars: &mut Vars) -> Result<()> {
+        match &pattern.kind {
+            PatternKind::Name(ident) => {
+                vars.insert(ident.name.to_string(), item);
+            }
+            PatternKind::Pair(key, value) => {
+                let entry = item
+                    .as_hash()
+                    .and_then(|hash| Some((hash.get("key")?, hash.get("value")?)));
+                let Some((k, v)) = entry else {
+                    return Err(Error::with_span(
+                        pattern.span.clone(),
+                        format!("expect a hash entry, not '{item}'"),
+                    ));
                 };
-                output.push(item);
+                self.bind_into(key, k.clone(), vars)?;
+                self.bind_into(value, v.clone(), vars)?;
+            }
+            PatternKind::Hash(fields) => {
+                let Some(hash) = item.as_hash() else {
+                    return Err(Error::with_span(
+                        pattern.span.clone(),
+                        format!("expect a 'hash', not '{}'", item.type_name()),
+                    ));
+                };
+                for (field, pattern) in fields {
+                    let Some(value) = hash.get(field.name.as_ref()) else {
+                        return Err(Error::with_span(
+                            field.span.clone(),
+                            format!("field '{}' is not found", field.name),
+                        ));
+                    };
+                    self.bind_into(pattern, value.clone(), vars)?;
+                }
+            }
+            PatternKind::List(patterns) => {
+                let Some(list) = item.as_list() else {
+                    return Err(Error::with_span(
+                        pattern.span.clone(),
+                        format!("expect a 'list', not '{}'", item.type_name()),
+                    ));
+                };
+                if list.len() != patterns.len() {
+                    return Err(Error::with_span(
+                        pattern.span.clone(),
+                        format!("expect {} items, not {}", patterns.len(), list.len()),
+                    ));
+                }
+                for (pattern, value) in patterns.iter().zip(list) {
+                    self.bind_into(pattern, value.clone(), vars)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Returns `item` with the parts bound by a pattern replaced by their
+    // values in this scope.
+    fn unbind(&self, pattern: &Pattern, item: Object) -> Result<Object> {
+        match &pattern.kind {
+            PatternKind::Name(ident) => Ok(self.var(&ident.name).unwrap()),
+            PatternKind::Pair(key, value) => {
+                let key = self.unbind(key, item.field("key")?)?;
+                let value = self.unbind(value, item.field("value")?)?;
+                Ok(Vars::from_iter([("key".to_owned(), key), ("value".to_owned(), value)]).into())
+            }
+            PatternKind::Hash(fields) => {
+                let mut item = item;
+                for (field, pattern) in fields {
+                    let value = self.unbind(pattern, item.field(&field.name)?)?;
+                    self.set_field(&mut item, &field.name, value)?;
+                }
+                Ok(item)
+            }
+            PatternKind::List(patterns) => {
+                let list = item.as_list().unwrap();
+                let list = patterns
+                    .iter()
+                    .zip(list)
+                    .map(|(pattern, value)| self.unbind(pattern, value.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(list.into())
+            }
+        }
+    }
+
+    // Evaluates the join sources once, so that the items that never matched
+    // can be remembered for full joins.
+    fn eval_join_sources<'a>(&self, from: &'a FromClause) -> Result<Vec<Join<'a>>> {
+        let mut joins = Vec::with_capacity(from.joins.len());
+        let mut bound = from.bind.names();
+        for clause in &from.joins {
+            let source = match clause.kind {
+                JoinKind::Lateral => None,
+                _ => Some(self.eval(&clause.source)?),
+            };
+            joins.push(Join::new(clause, source, &bound)?);
+            bound.extend(clause.bind.names());
+        }
+        Ok(joins)
+    }
+
+    // Extends `vars` with the items of the first join that match its on
+    // clause, and recurses into the remaining joins. Returns false if `f`
+    // asked to stop.
+    //
+    // `f` is also passed the indices of the joined items in the row, which
+    // are none for the names bound to null.
+    //
+    // `padded` are the names bound to null because nothing matched them. An
+    // on clause that refers to any of them matches nothing.
+    fn eval_joins(
+        &self,
+        from: &FromClause,
+        joins: &mut [Join],
+        vars: Vars,
+        padded: &[&str],
+        indices: &mut Vec<Option<usize>>,
+        f: &mut impl FnMut(&Runtime, Object, &[Option<usize>]) -> Result<bool>,
+    ) -> Result<bool> {
+        let Some((join, rest)) = joins.split_first_mut() else {
+            let inner = self.enter(vars.clone());
+            if let Some(filter) = from.filter.as_ref() {
+                if !inner.eval_filter(filter)? {
+                    return Ok(true);
+                }
+            }
+            return f(&inner, vars.into(), indices);
+        };
+        if join.clause.kind == JoinKind::Lateral {
+            let source = self.enter(vars.clone()).eval(&join.clause.source)?;
+            for item in source.iter()? {
+                let mut vars = vars.clone();
+                vars.extend(self.bind(&join.clause.bind, item)?);
+                indices.push(None);
+                let more = self.eval_joins(from, rest, vars, padded, indices, f)?;
+                indices.pop();
+                if !more {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        let mut found = false;
+        let candidates = match join.clause.filter.as_ref() {
+            Some(filter) if join::refers_to(filter, padded) => Vec::new(),
+            _ => join.candidates(self, &vars),
+        };
+        for i in candidates {
+            let mut vars = vars.clone();
+            vars.extend(self.bind(&join.clause.bind, join.items[i].clone())?);
+            if let Some(filter) = join.clause.filter.as_ref() {
+                if !self.enter(vars.clone()).eval_filter(filter)? {
+                    continue;
+                }
+            }
+            found = true;
+            join.matched[i] = true;
+            indices.push(Some(i));
+            let more = self.eval_joins(from, rest, vars, padded, indices, f)?;
+            indices.pop();
+            if !more {
+                return Ok(false);
+            }
+        }
+        if !found && join.clause.kind != JoinKind::Inner {
+            let mut vars = vars;
+            let mut padded = padded.to_vec();
+            for name in join.clause.bind.names() {
+                vars.insert(name.to_owned(), self.builtin.null.clone());
+                padded.push(name);
+            }
+            indices.push(None);
+            let more = self.eval_joins(from, rest, vars, &padded, indices, f)?;
+            indices.pop();
+            return Ok(more);
+        }
+        Ok(true)
+    }
+
+    // Evaluates an optional limit or offset to a non-negative count.
+    fn eval_count(&self, expr: Option<&Expr>) -> Result<Option<usize>> {
+        let Some(expr) = expr else {
+            return Ok(None);
+        };
+        let value = self.eval(expr)?;
+        match value.as_i64() {
+            Some(n) if n >= 0 => Ok(Some(n as usize)),
+            _ => Err(Error::with_span(
+                expr.span.clone(),
+                format!("expect a non-negative 'i64', not '{value}'"),
+            )),
+        }
+    }
+
+    fn eval_setop(&self, op: &Spanned<SetOp>, lhs: &Expr, rhs: &Expr) -> Result<Object> {
+        // Neither operand is evaluated if one of them would modify its source.
+        for expr in [lhs, rhs] {
+            if !matches!(expr.kind, ExprKind::Select(_) | ExprKind::SetOp(..)) {
+                return Err(Error::with_span(
+                    expr.span.clone(),
+                    "expect a select expression as an operand of a set operation",
+                ));
+            }
+        }
+        let this = self.eval(lhs)?;
+        let other = self.eval(rhs)?;
+        let mut seen = ObjectMap::new();
+        let mut output = Vec::new();
+        match op.kind {
+            SetOp::Union => {
+                for item in this.iter()?.chain(other.iter()?) {
+                    if seen.insert(item.clone(), ())? {
+                        output.push(item);
+                    }
+                }
+            }
+            SetOp::Intersect | SetOp::Except => {
+                let mut other_items = ObjectMap::new();
+                for item in other.iter()? {
+                    other_items.insert(item, ())?;
+                }
+                let keep = op.kind == SetOp::Intersect;
+                for item in this.iter()? {
+                    if other_items.contains_key(&item)? == keep && seen.insert(item.clone(), ())? {
+                        output.push(item);
+                    }
+                }
             }
         }
         Ok(output.into())
     }
 
+    // Returns a function that evaluates the body in a scope of the current
+    // one, and declares it by its name if any.
+    fn eval_func(&self, def: &Rc<FuncDef>) -> Result<Object> {
+        let scope = Runtime {
+            group: None,
+            ..self.clone()
+        };
+        let def = def.clone();
+        let name = def.func().name.as_ref().map(|ident| ident.name.to_string());
+        let object: Object =
+            (move |this: &Object, args: &[Object]| scope.call_func(this, &def, args)).into();
+        if let Some(name) = name {
+            self.declare_var(name, object.clone());
+        }
+        Ok(object)
+    }
+
+    fn call_func(&self, this: &Object, def: &FuncDef, args: &[Object]) -> Result<Object> {
+        let func = def.func();
+        if args.len() != func.params.len() {
+            return Err(Error::new(format!(
+                "expect {} arguments, not {}",
+                func.params.len(),
+                args.len()
+            )));
+        }
+        let eval = || {
+            let mut vars = Vars::new();
+            // A named function can call itself by its name.
+            if let Some(ident) = &func.name {
+                vars.insert(ident.name.to_string(), this.clone());
+            }
+            for (param, arg) in func.params.iter().zip(args) {
+                self.bind_into(param, arg.clone(), &mut vars)?;
+            }
+            self.enter(vars).eval(&func.body)
+        };
+        // The function may be from an earlier input, so errors refer to its
+        // source.
+        eval().map_err(|e| e.in_source(def.source()))
+    }
+
     fn eval_filter(&self, filter: &Expr) -> Result<bool> {
-        self.eval(filter)?.as_bool().ok_or_else(|| {
+        self.eval_bool(filter, "where clause")
+    }
+
+    fn eval_bool(&self, expr: &Expr, what: &str) -> Result<bool> {
+        self.eval(expr)?.as_bool().ok_or_else(|| {
             Error::with_span(
-                filter.span.clone(),
-                "where clause should be a boolean expression",
+                expr.span.clone(),
+                format!("{what} should be a boolean expression"),
             )
         })
     }
 
+    fn eval_block(&self, block: &Block) -> Result<Object> {
+        let value = self.exec_block(block, Vars::new())?;
+        Ok(value.unwrap_or_else(|| self.builtin.null.clone()))
+    }
+
+    fn eval_if(&self, if_: &If) -> Result<Object> {
+        if self.eval_bool(&if_.cond, "if condition")? {
+            self.eval(&if_.then)
+        } else if let Some(else_) = &if_.else_ {
+            self.eval(else_)
+        } else {
+            Ok(self.builtin.null.clone())
+        }
+    }
+
     fn eval_assign(&self, lhs: &Expr, rhs: &Expr) -> Result<Object> {
         let value = self.eval(rhs)?;
         match &lhs.kind {
             ExprKind::Name(ident) => {
-                self.set_var(ident.name, value.clone());
+                self.set_var(&ident.name, value.clone())?;
                 Ok(value)
             }
             ExprKind::Index(expr, index) => {
                 let mut this = self.eval(expr)?;
                 let index = self.eval(index)?;
-                this.set_index(&index, value.clone())?;
+                self.set_index(&mut this, &index, value.clone())?;
                 Ok(value)
             }
             ExprKind::Field(expr, field) => {
                 let mut this = self.eval(expr)?;
-                this.set_field(field.name, value.clone())?;
+                self.set_field(&mut this, &field.name, value.clone())?;
                 Ok(value)
             }
             _ => Err(Error::with_span(
@@ -343,7 +1333,7 @@ impl Runtime {
             ExprKind::Name(ident) => {
                 let old_value = self.eval_name(ident)?;
                 let new_value = old_value.binop(op.kind, &value)?;
-                self.set_var(ident.name, new_value.clone());
+                self.set_var(&ident.name, new_value.clone())?;
                 Ok(new_value)
             }
             ExprKind::Index(expr, index) => {
@@ -351,14 +1341,14 @@ impl Runtime {
                 let index = self.eval(index)?;
                 let old_value = this.index(&index)?;
                 let new_value = old_value.binop(op.kind, &value)?;
-                this.set_index(&index, new_value.clone())?;
+                self.set_index(&mut this, &index, new_value.clone())?;
                 Ok(new_value)
             }
             ExprKind::Field(expr, field) => {
                 let mut this = self.eval(expr)?;
-                let old_value = this.field(field.name)?;
+                let old_value = self.field(&this, field)?;
                 let new_value = old_value.binop(op.kind, &value)?;
-                this.set_field(field.name, new_value.clone())?;
+                self.set_field(&mut this, &field.name, new_value.clone())?;
                 Ok(new_value)
             }
             _ => Err(Error::with_span(
@@ -369,31 +1359,165 @@ impl Runtime {
     }
 }
 
+// Returns an error for the first join of the unsupported kinds.
+//
+// Full joins produce rows without a from item, which can't be updated or
+// deleted. Lateral sources refer to the from item, which is updated on a copy.
+fn check_joins(from: &FromClause, expr: &str, unsupported: &[JoinKind]) -> Result<()> {
+    match from
+        .joins
+        .iter()
+        .find(|join| unsupported.contains(&join.kind))
+    {
+        Some(join) => {
+            let what = match join.kind {
+                JoinKind::Lateral => "lateral source",
+                _ => "full join",
+            };
+            Err(Error::with_span(
+                join.span.clone(),
+                format!("{what} is not supported in {expr} expressions"),
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+// Returns the value of an updated hash entry, if it is still an entry with
+// the key.
+fn entry_value(key: &str, entry: &Object) -> Option<Object> {
+    let entry = entry.as_hash()?;
+    if entry.get("key")?.as_str()? != key {
+        return None;
+    }
+    entry.get("value").cloned()
+}
+
+// Returns whether an expression refers to a variable or to a part of one,
+// rather than to a temporary value.
+fn is_place(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Name(_) => true,
+        ExprKind::Field(expr, _) | ExprKind::SafeField(expr, _) | ExprKind::Index(expr, _) => {
+            is_place(expr)
+        }
+        _ => false,
+    }
+}
+
+// Returns whether a pattern binds the whole item to the name.
+fn binds_item(pattern: &Pattern, ident: &Ident) -> bool {
+    matches!(&pattern.kind, PatternKind::Name(name) if name.name == ident.name)
+}
+
+fn invalid_delete_target(target: &Expr) -> Error {
+    Error::with_span(
+        target.span.clone(),
+        "delete target should be a bound name, a field or an index of a hash, or an index of a list",
+    )
+}
+
+fn compare_sort_keys(keys: &[SortKey], a: &[Object], b: &[Object]) -> Result<Ordering> {
+    for (key, (a, b)) in keys.iter().zip(a.iter().zip(b)) {
+        let ord = a
+            .compare(b)
+            .map_err(|e| Error::with_span(key.expr.span.clone(), e.message))?;
+        let ord = if key.desc { ord.reverse() } else { ord };
+        if ord != Ordering::Equal {
+            return Ok(ord);
+        }
+    }
+    Ok(Ordering::Equal)
+}
+
+// What a delete expression deletes
+#[derive(Clone, Copy)]
+enum DeleteTarget<'a> {
+    // The items of the from clause
+    From,
+    // The items of the join clause at the index
+    Join(usize),
+    // The entries of hashes or the items of lists
+    Index(&'a Expr<'a>, &'a Expr<'a>),
+    Field(&'a Expr<'a>, &'a str),
+}
+
+// The key of an entry to delete
+enum EntryKey {
+    Field(String),
+    // The index of a list item from the start
+    Index(usize),
+}
+
+// The output of a select expression
+struct Output {
+    items: Vec<Object>,
+    // The items that have been seen in a distinct select
+    seen: Option<ObjectMap<()>>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl Output {
+    // Returns true if the next item should be skipped before it is projected.
+    fn skip(&mut self) -> bool {
+        // Distinct items are only known after projection.
+        if self.seen.is_none() && self.offset > 0 {
+            self.offset -= 1;
+            return true;
+        }
+        false
+    }
+
+    // Returns false if the limit is reached.
+    fn push(&mut self, item: Object) -> Result<bool> {
+        if let Some(seen) = self.seen.as_mut() {
+            if !seen.insert(item.clone(), ())? {
+                return Ok(true);
+            }
+            if self.offset > 0 {
+                self.offset -= 1;
+                return Ok(true);
+            }
+        }
+        self.items.push(item);
+        Ok(self.limit.is_none_or(|n| self.items.len() < n))
+    }
+}
+
 impl Default for Runtime {
     fn default() -> Self {
-        Self::new()
+        Self::new(Options::default())
     }
 }
 
 struct Builtin {
+    options: Options,
     null: Object,
     true_: Object,
     false_: Object,
     modules: HashMap<String, Object>,
+    functions: HashMap<String, Object>,
+    aggregates: HashMap<String, Object>,
+    journal: Journal,
 }
 
 impl Builtin {
-    fn new() -> Self {
+    fn new(options: Options) -> Self {
         Self {
+            options,
             null: ().into(),
             true_: true.into(),
             false_: false.into(),
             modules: [("json".into(), json::module())].into(),
+            functions: collection::functions(),
+            aggregates: aggregate::functions(),
+            journal: Journal::default(),
         }
     }
 }
 
-type Vars = HashMap<String, Object>;
+type Vars = IndexMap<String, Object>;
 
 #[derive(Default)]
 struct Closure {
@@ -410,7 +1534,19 @@ impl Closure {
         })
     }
 
-    fn set_var(&mut self, name: String, value: Object) {
+    fn declare_var(&mut self, name: String, value: Object) {
         self.vars.insert(name, value);
     }
+
+    // Returns the innermost scope that has the name.
+    fn scope_of(this: &Rc<RefCell<Closure>>, name: &str) -> Option<Rc<RefCell<Closure>>> {
+        let mut scope = this.clone();
+        loop {
+            if scope.borrow().vars.contains_key(name) {
+                return Some(scope);
+            }
+            let outer = scope.borrow().outer.clone()?;
+            scope = outer;
+        }
+    }
 }
@@ -0,0 +1,85 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+use crate::{Object, Result};
+
+/// A map keyed by objects that keeps entries in insertion order.
+///
+/// Keys are matched by equality, so they must be hashable.
+pub(crate) struct ObjectMap<V> {
+    buckets: HashMap<u64, Vec<usize>>,
+    entries: Vec<(Object, V)>,
+}
+
+impl<V> ObjectMap<V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: HashMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub(crate) fn contains_key(&self, key: &Object) -> Result<bool> {
+        let hash = hash(key)?;
+        Ok(self.find(hash, key).is_some())
+    }
+
+    pub(crate) fn get(&self, key: &Object) -> Result<Option<&V>> {
+        let hash = hash(key)?;
+        Ok(self.find(hash, key).map(|i| &self.entries[i].1))
+    }
+
+    /// Inserts an entry if the key is not present.
+    ///
+    /// Returns whether the entry is inserted.
+    pub(crate) fn insert(&mut self, key: Object, value: V) -> Result<bool> {
+        let mut inserted = false;
+        self.get_or_insert_with(key, || {
+            inserted = true;
+            value
+        })?;
+        Ok(inserted)
+    }
+
+    pub(crate) fn get_or_insert_with(
+        &mut self,
+        key: Object,
+        f: impl FnOnce() -> V,
+    ) -> Result<&mut V> {
+        let hash = hash(&key)?;
+        let i = match self.find(hash, &key) {
+            Some(i) => i,
+            None => {
+                self.buckets
+                    .entry(hash)
+                    .or_default()
+                    .push(self.entries.len());
+                self.entries.push((key, f()));
+                self.entries.len() - 1
+            }
+        };
+        Ok(&mut self.entries[i].1)
+    }
+
+    fn find(&self, hash: u64, key: &Object) -> Option<usize> {
+        self.buckets
+            .get(&hash)
+            .and_then(|bucket| bucket.iter().copied().find(|&i| &self.entries[i].0 == key))
+    }
+}
+
+impl<V> IntoIterator for ObjectMap<V> {
+    type Item = (Object, V);
+    type IntoIter = std::vec::IntoIter<(Object, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+fn hash(key: &Object) -> Result<u64> {
+    let mut state = DefaultHasher::new();
+    key.hash(&mut state)?;
+    Ok(state.finish())
+}
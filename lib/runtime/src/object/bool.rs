@@ -32,6 +32,15 @@ thread_local! {
                 let data = unsafe { this.0.data::<bool>() };
                 write!(f, "{}", data)
             },
+            compare: |this, other| {
+                let data = unsafe { this.0.data::<bool>() };
+                other.as_bool().map(|x| data.cmp(&x))
+            },
+            hash: |this, mut state| {
+                let data = unsafe { this.0.data::<bool>() };
+                data.hash(&mut state);
+                Ok(())
+            },
             ..Default::default()
         },
     });
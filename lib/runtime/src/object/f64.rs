@@ -30,6 +30,7 @@ thread_local! {
             name: "f64".into(),
             format,
             compare,
+            hash,
             arithmetic: ArithmeticMethods {
                 neg: |this| unop(this, |x| -x),
                 add: |this, other| binop(this, other, "+", |x, y| x + y),
@@ -51,7 +52,15 @@ fn format(this: &Object, f: &mut fmt::Formatter) -> fmt::Result {
 
 fn compare(this: &Object, other: &Object) -> Option<Ordering> {
     let data = unsafe { this.0.data::<f64>() };
-    other.as_f64().map(|x| data.partial_cmp(&x).unwrap())
+    other.as_f64().and_then(|x| data.partial_cmp(&x))
+}
+
+fn hash(this: &Object, state: &mut dyn Hasher) -> Result<()> {
+    let data = unsafe { this.0.data::<f64>() };
+    // 0.0 and -0.0 are equal, so they must have the same hash.
+    let data = if *data == 0.0 { 0.0 } else { *data };
+    state.write_u64(data.to_bits());
+    Ok(())
 }
 
 fn unop(this: &Object, f: fn(f64) -> f64) -> Result<Object> {
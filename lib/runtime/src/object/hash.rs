@@ -1,9 +1,35 @@
 use std::cell::UnsafeCell;
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+
+use indexmap::IndexMap;
 
 use super::*;
 
-type Hash = HashMap<String, Object>;
+// Entries are kept in insertion order, so that hashes are formatted and
+// iterated in a stable order.
+type Hash = IndexMap<String, Object>;
+
+impl Object {
+    fn is_hash(&self) -> bool {
+        TYPE.with(|t| self.0.is_type(t))
+    }
+
+    pub(crate) fn as_hash(&self) -> Option<&Hash> {
+        if self.is_hash() {
+            Some(unsafe { self.0.data::<Hash>() })
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn as_hash_mut(&mut self) -> Option<&mut Hash> {
+        if self.is_hash() {
+            Some(unsafe { self.0.data_mut::<Hash>() })
+        } else {
+            None
+        }
+    }
+}
 
 impl From<Hash> for Object {
     fn from(value: Hash) -> Self {
@@ -32,6 +58,9 @@ thread_local! {
             set_index,
             field,
             set_field,
+            compare,
+            hash,
+            iter,
             contains,
             ..Default::default()
         },
@@ -80,6 +109,48 @@ fn set_field(this: &mut Object, field: &str, value: Object) -> Result<()> {
     Ok(())
 }
 
+// Hashes are only comparable for equality.
+fn compare(this: &Object, other: &Object) -> Option<Ordering> {
+    let hash = unsafe { this.0.data::<Hash>() };
+    if !other.is_hash() {
+        return None;
+    }
+    let other = unsafe { other.0.data::<Hash>() };
+    let equal = hash.len() == other.len()
+        && hash
+            .iter()
+            .all(|(k, v)| other.get(k).is_some_and(|x| v == x));
+    equal.then_some(Ordering::Equal)
+}
+
+fn hash(this: &Object, state: &mut dyn Hasher) -> Result<()> {
+    let hash = unsafe { this.0.data::<Hash>() };
+    // Entries are unordered, so their hashes are combined in an
+    // order-independent way.
+    let mut sum = 0u64;
+    for (k, v) in hash {
+        let mut entry = DefaultHasher::new();
+        std::hash::Hash::hash(k, &mut entry);
+        v.hash(&mut entry)?;
+        sum = sum.wrapping_add(entry.finish());
+    }
+    state.write_usize(hash.len());
+    state.write_u64(sum);
+    Ok(())
+}
+
+// Iterates the entries as `{key, value}` hashes.
+fn iter(this: &Object) -> Result<Iter<'_>> {
+    let hash = unsafe { this.0.data::<Hash>() };
+    Ok(Box::new(hash.iter().map(|(k, v)| {
+        Hash::from_iter([
+            ("key".into(), k.as_str().into()),
+            ("value".into(), v.clone()),
+        ])
+        .into()
+    })))
+}
+
 fn contains(this: &Object, other: &Object) -> Result<bool> {
     let hash = unsafe { this.0.data::<Hash>() };
     Ok(other
@@ -30,6 +30,7 @@ thread_local! {
             name: "i64".into(),
             format,
             compare,
+            hash,
             arithmetic: ArithmeticMethods {
                 not: |this| unop(this, |x| !x),
                 or: |this, other| binop(this, other, "|", |x, y| x | y),
@@ -59,6 +60,12 @@ fn compare(this: &Object, other: &Object) -> Option<Ordering> {
     other.as_i64().map(|x| data.cmp(&x))
 }
 
+fn hash(this: &Object, mut state: &mut dyn Hasher) -> Result<()> {
+    let data = unsafe { this.0.data::<i64>() };
+    data.hash(&mut state);
+    Ok(())
+}
+
 fn unop(this: &Object, f: fn(i64) -> i64) -> Result<Object> {
     let data = unsafe { this.0.data::<i64>() };
     Ok(f(*data).into())
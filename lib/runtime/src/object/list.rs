@@ -8,6 +8,22 @@ impl Object {
     fn is_list(&self) -> bool {
         TYPE.with(|t| self.0.is_type(t))
     }
+
+    pub(crate) fn as_list(&self) -> Option<&[Object]> {
+        if self.is_list() {
+            Some(unsafe { self.0.data::<List>() })
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn as_list_mut(&mut self) -> Option<&mut Vec<Object>> {
+        if self.is_list() {
+            Some(unsafe { self.0.data_mut::<List>() })
+        } else {
+            None
+        }
+    }
 }
 
 impl From<List> for Object {
@@ -29,6 +45,8 @@ thread_local! {
             format,
             index,
             set_index,
+            compare,
+            hash,
             iter,
             iter_mut,
             insert,
@@ -53,6 +71,9 @@ fn format(this: &Object, f: &mut fmt::Formatter) -> fmt::Result {
 
 fn index(this: &Object, index: &Object) -> Result<Object> {
     let list = unsafe { this.0.data::<List>() };
+    if let Some(range) = index.as_range() {
+        return range.resolve(list.len()).map(|r| list[r].to_vec().into());
+    }
     list_index(index, list.len()).map(|i| unsafe { list.get_unchecked(i).clone() })
 }
 
@@ -61,7 +82,7 @@ fn set_index(this: &mut Object, index: &Object, value: Object) -> Result<()> {
     list_index(index, list.len()).map(|i| unsafe { *list.get_unchecked_mut(i) = value })
 }
 
-fn list_index<'a>(index: &Object, len: usize) -> Result<usize> {
+fn list_index(index: &Object, len: usize) -> Result<usize> {
     match index.as_i64() {
         Some(i) => {
             let i = if i >= 0 {
@@ -82,12 +103,33 @@ fn list_index<'a>(index: &Object, len: usize) -> Result<usize> {
     }
 }
 
-fn iter(this: &Object) -> Result<Iter> {
+fn compare(this: &Object, other: &Object) -> Option<Ordering> {
+    let list = unsafe { this.0.data::<List>() };
+    if !other.is_list() {
+        return None;
+    }
+    let other = unsafe { other.0.data::<List>() };
+    for (x, y) in list.iter().zip(other) {
+        match x.partial_cmp(y)? {
+            Ordering::Equal => {}
+            ord => return Some(ord),
+        }
+    }
+    Some(list.len().cmp(&other.len()))
+}
+
+fn hash(this: &Object, state: &mut dyn Hasher) -> Result<()> {
+    let list = unsafe { this.0.data::<List>() };
+    state.write_usize(list.len());
+    list.iter().try_for_each(|x| x.hash(state))
+}
+
+fn iter(this: &Object) -> Result<Iter<'_>> {
     let list = unsafe { this.0.data::<List>() };
-    Ok(Box::new(list.iter()))
+    Ok(Box::new(list.iter().cloned()))
 }
 
-fn iter_mut(this: &mut Object) -> Result<IterMut> {
+fn iter_mut(this: &mut Object) -> Result<IterMut<'_>> {
     let list = unsafe { this.0.data_mut::<List>() };
     Ok(Box::new(list.iter_mut()))
 }
@@ -1,6 +1,7 @@
 use std::cell::{Cell, UnsafeCell};
 use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ptr::NonNull;
 
 use sigma_parser::ast::*;
@@ -12,15 +13,18 @@ mod hash;
 mod i64;
 mod list;
 mod null;
+mod range;
 mod str;
 
+pub(crate) use range::Range;
+
 use crate::{Error, Result};
 
 #[derive(Clone)]
 pub struct Object(RawObject<()>);
 
 impl Object {
-    fn type_name(&self) -> &str {
+    pub(crate) fn type_name(&self) -> &str {
         &self.0.type_data().name
     }
 
@@ -70,8 +74,8 @@ impl Object {
 
     pub(crate) fn cmpop(&self, op: CmpOp, other: &Object) -> Result<Object> {
         let value = match op {
-            CmpOp::Eq => self.compare(other)? == Ordering::Equal,
-            CmpOp::Ne => self.compare(other)? != Ordering::Equal,
+            CmpOp::Eq => self == other,
+            CmpOp::Ne => self != other,
             CmpOp::Lt => self.compare(other)? == Ordering::Less,
             CmpOp::Le => self.compare(other)? != Ordering::Greater,
             CmpOp::Gt => self.compare(other)? == Ordering::Greater,
@@ -82,7 +86,7 @@ impl Object {
         Ok(value.into())
     }
 
-    fn compare(&self, other: &Object) -> Result<Ordering> {
+    pub(crate) fn compare(&self, other: &Object) -> Result<Ordering> {
         (self.0.type_data().compare)(self, other).ok_or_else(|| {
             Error::new(format!(
                 "'{}' cannot be compared with '{}'",
@@ -92,11 +96,20 @@ impl Object {
         })
     }
 
-    pub(crate) fn iter(&self) -> Result<Iter> {
+    pub(crate) fn hash(&self, state: &mut dyn Hasher) -> Result<()> {
+        (self.0.type_data().hash)(self, state)
+    }
+
+    // Returns an identity of the object, which is shared by its clones.
+    pub(crate) fn id(&self) -> usize {
+        self.0 .0.as_ptr() as usize
+    }
+
+    pub(crate) fn iter(&self) -> Result<Iter<'_>> {
         (self.0.type_data().iter)(self)
     }
 
-    pub(crate) fn iter_mut(&mut self) -> Result<IterMut> {
+    pub(crate) fn iter_mut(&mut self) -> Result<IterMut<'_>> {
         (self.0.type_data().iter_mut)(self)
     }
 
@@ -165,6 +178,7 @@ impl<T> RawObject<T> {
         unsafe { self.0.as_ref() }
     }
 
+    #[allow(clippy::mut_from_ref)]
     unsafe fn as_mut(&self) -> &mut Inner<T> {
         &mut *self.0.as_ptr()
     }
@@ -224,6 +238,9 @@ struct TypeData {
 
     compare: fn(&Object, &Object) -> Option<Ordering>,
 
+    // Objects that compare equal must have the same hash.
+    hash: fn(&Object, &mut dyn Hasher) -> Result<()>,
+
     iter: for<'a> fn(&'a Object) -> Result<Iter<'a>>,
     iter_mut: fn(&mut Object) -> Result<IterMut>,
 
@@ -245,6 +262,7 @@ impl Default for TypeData {
             field: |this, _| Err(unsupported_operation(this, "field access")),
             set_field: |this, _, _| Err(unsupported_operation(this, "field access")),
             compare: |_, _| None,
+            hash: |this, _| Err(unsupported(this, "is not hashable")),
             iter: |this| Err(unsupported(this, "is not iterable")),
             iter_mut: |this| Err(unsupported(this, "is not iterable")),
             insert: |this, _| Err(unsupported_operation(this, "insert")),
@@ -255,7 +273,7 @@ impl Default for TypeData {
     }
 }
 
-pub(crate) type Iter<'a> = Box<dyn Iterator<Item = &'a Object> + 'a>;
+pub(crate) type Iter<'a> = Box<dyn Iterator<Item = Object> + 'a>;
 pub(crate) type IterMut<'a> = Box<dyn Iterator<Item = &'a mut Object> + 'a>;
 
 struct ArithmeticMethods {
@@ -305,7 +323,7 @@ fn unsupported_operation(this: &Object, op: &str) -> Error {
 }
 
 thread_local! {
-    static INIT: Cell<bool> = Cell::new(false);
+    static INIT: Cell<bool> = const { Cell::new(false) };
 
     static TYPE_TYPE: RawObject<TypeData> = unsafe {
         RawObject::from_ptr(TYPE_TYPE_DATA.with(|x| x.get()))
@@ -2,6 +2,12 @@ use std::cell::UnsafeCell;
 
 use super::*;
 
+impl Object {
+    pub(crate) fn is_null(&self) -> bool {
+        TYPE.with(|t| self.0.is_type(t))
+    }
+}
+
 impl From<()> for Object {
     fn from(value: ()) -> Self {
         Self(RawObject::new(TYPE.with(|t| t.clone()), value))
@@ -19,6 +25,8 @@ thread_local! {
         data: TypeData {
             name: "null".into(),
             format: |_, f| write!(f, "null"),
+            compare: |_, other| other.is_null().then_some(Ordering::Equal),
+            hash: |_, _| Ok(()),
             ..Default::default()
         }
     });
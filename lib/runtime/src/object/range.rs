@@ -0,0 +1,116 @@
+use std::cell::UnsafeCell;
+use std::ops;
+
+use super::*;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Range {
+    pub(crate) start: Option<i64>,
+    pub(crate) end: Option<i64>,
+    pub(crate) inclusive: bool,
+}
+
+impl Range {
+    /// Resolves the range against a sequence of `len` items.
+    ///
+    /// Negative bounds count from the end of the sequence.
+    pub(crate) fn resolve(&self, len: usize) -> Result<ops::Range<usize>> {
+        let bound = |i: i64| {
+            let j = if i >= 0 { i } else { len as i64 + i };
+            if j >= 0 && j as usize <= len {
+                Ok(j as usize)
+            } else {
+                Err(Error::new(format!("range '{self}' out of bounds")))
+            }
+        };
+        let start = self.start.map(bound).transpose()?.unwrap_or(0);
+        let end = match self.end {
+            Some(end) => bound(end)? + self.inclusive as usize,
+            None => len,
+        };
+        if start <= end && end <= len {
+            Ok(start..end)
+        } else {
+            Err(Error::new(format!("range '{self}' out of bounds")))
+        }
+    }
+
+    fn contains(&self, i: i64) -> bool {
+        let start = self.start.is_none_or(|x| x <= i);
+        let end = match self.end {
+            Some(x) if self.inclusive => i <= x,
+            Some(x) => i < x,
+            None => true,
+        };
+        start && end
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{start}")?;
+        }
+        f.write_str(if self.inclusive { "..=" } else { ".." })?;
+        if let Some(end) = self.end {
+            write!(f, "{end}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Object {
+    pub(crate) fn as_range(&self) -> Option<Range> {
+        if TYPE.with(|t| self.0.is_type(t)) {
+            Some(unsafe { *self.0.data::<Range>() })
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Range> for Object {
+    fn from(value: Range) -> Self {
+        Self(RawObject::new(TYPE.with(|t| t.clone()), value))
+    }
+}
+
+thread_local! {
+    static TYPE: RawObject<TypeData> = unsafe {
+        RawObject::from_ptr(TYPE_DATA.with(|x| x.get()))
+    };
+
+    static TYPE_DATA: UnsafeCell<Inner<TypeData>> = UnsafeCell::new(Inner {
+        rc: 1,
+        ty: super::TYPE_TYPE.with(|x| x.clone()),
+        data: TypeData {
+            name: "range".into(),
+            format,
+            iter,
+            contains,
+            ..Default::default()
+        },
+    });
+}
+
+fn format(this: &Object, f: &mut fmt::Formatter) -> fmt::Result {
+    let data = unsafe { this.0.data::<Range>() };
+    write!(f, "{}", data)
+}
+
+fn iter(this: &Object) -> Result<Iter<'_>> {
+    let data = unsafe { this.0.data::<Range>() };
+    let start = data
+        .start
+        .ok_or_else(|| Error::new(format!("range '{data}' without a start is not iterable")))?;
+    match data.end {
+        Some(end) if data.inclusive => Ok(Box::new((start..=end).map(Into::into))),
+        Some(end) => Ok(Box::new((start..end).map(Into::into))),
+        None => Ok(Box::new((start..).map(Into::into))),
+    }
+}
+
+fn contains(this: &Object, other: &Object) -> Result<bool> {
+    let data = unsafe { this.0.data::<Range>() };
+    Ok(other.as_i64().map(|x| data.contains(x)).unwrap_or(false))
+}
@@ -35,7 +35,9 @@ thread_local! {
         data: TypeData {
             name: "str".into(),
             format,
+            index,
             compare,
+            hash,
             contains,
             ..Default::default()
         },
@@ -44,7 +46,25 @@ thread_local! {
 
 fn format(this: &Object, f: &mut fmt::Formatter) -> fmt::Result {
     let data = unsafe { this.0.data::<String>() };
-    write!(f, "\"{}\"", data)
+    write!(f, "{:?}", data)
+}
+
+fn index(this: &Object, index: &Object) -> Result<Object> {
+    let data = unsafe { this.0.data::<String>() };
+    let range = index.as_range().ok_or_else(|| {
+        Error::new(format!(
+            "str index must be 'range', not '{}'",
+            index.type_name()
+        ))
+    })?;
+    let len = data.chars().count();
+    let r = range.resolve(len)?;
+    Ok(data
+        .chars()
+        .skip(r.start)
+        .take(r.len())
+        .collect::<String>()
+        .into())
 }
 
 fn compare(this: &Object, other: &Object) -> Option<Ordering> {
@@ -52,6 +72,12 @@ fn compare(this: &Object, other: &Object) -> Option<Ordering> {
     other.as_str().map(|x| data.as_str().cmp(x))
 }
 
+fn hash(this: &Object, mut state: &mut dyn Hasher) -> Result<()> {
+    let data = unsafe { this.0.data::<String>() };
+    data.hash(&mut state);
+    Ok(())
+}
+
 fn contains(this: &Object, other: &Object) -> Result<bool> {
     let data = unsafe { this.0.data::<String>() };
     Ok(other.as_str().map(|x| data.contains(x)).unwrap_or(false))
@@ -0,0 +1,35 @@
+mod util;
+use util::*;
+
+#[test]
+fn if_then_else() {
+    let src = r#"from x in [1, 5, 10] select if x > 4 then "big" else "small""#;
+    assert_eq!(eval(src), r#"["small", "big", "big"]"#);
+    assert_eq!(eval("if false then 1"), "null");
+    assert_eq!(eval("if true then 1 else 2 + 3"), "1");
+}
+
+#[test]
+fn if_blocks() {
+    let src = r#"x = 3; if x > 5 { "a" } else if x > 2 { "b" } else { "c" }"#;
+    assert_eq!(eval(src), r#""b""#);
+    assert_eq!(eval("if true { let y = 2; y * 2 }"), "4");
+    assert_eq!(eval("if false { 1 }"), "null");
+    // A block runs in its own scope.
+    assert_eq!(eval("y = 1; if true { let y = 2 }; y"), "1");
+}
+
+#[test]
+fn condition_is_boolean() {
+    let (span, message) = eval_err("if 1 then 2");
+    assert_eq!(span, "1");
+    assert_eq!(message, "if condition should be a boolean expression");
+    let (span, _) = eval_err("if null { 1 }");
+    assert_eq!(span, "null");
+}
+
+#[test]
+fn only_the_taken_branch_is_evaluated() {
+    assert_eq!(eval("n = 0; if true then n += 1 else n += 10; n"), "1");
+    assert_eq!(eval("if true then 1 else nope"), "1");
+}
@@ -0,0 +1,87 @@
+mod util;
+use util::*;
+
+#[test]
+fn delete_items() {
+    let src = "list = [1, 2, 3]; from x in list where x % 2 == 1 delete x";
+    assert_eq!(eval(src), "2");
+    let src = "list = [1, 2, 3]; from x in list where x % 2 == 1 delete x; list";
+    assert_eq!(eval(src), "[2]");
+}
+
+#[test]
+fn delete_from_nested_source() {
+    let src = "h = {a: [1, 2, 3]}; from x in h.a where x > 1 delete x; h";
+    assert_eq!(eval(src), "{a: [1]}");
+}
+
+#[test]
+fn delete_joined_items() {
+    let src = "a = [1, 2]; b = [2, 3, 2]; from x in a join y in b on x == y delete y; [a, b]";
+    assert_eq!(eval(src), "[[1, 2], [3]]");
+}
+
+#[test]
+fn delete_hash_entries() {
+    let src = r#"h = {a: 1, b: 2}; from k in ["a", "c"] delete h[k]; h"#;
+    assert_eq!(eval(src), "{b: 2}");
+    let src = "h = {a: {b: 1}}; from x in [1] delete h.a.b; h";
+    assert_eq!(eval(src), "{a: {}}");
+}
+
+#[test]
+fn delete_list_items_by_index() {
+    let src = "h = {list: [1, 2, 3, 4]}; from i in [0, 2, -1, 0] delete h.list[i]";
+    assert_eq!(eval(src), "3");
+    let src = "h = {list: [1, 2, 3, 4]}; from i in [0, 2, -1, 0] delete h.list[i]; h";
+    assert_eq!(eval(src), "{list: [2]}");
+}
+
+#[test]
+fn delete_out_of_bounds_index() {
+    let results = exec_all("list = [1, 2]; from i in [0, 2] delete list[i]; list");
+    assert_eq!(
+        results[1],
+        Err("expect an index of the list, not '2'".into())
+    );
+    assert_eq!(results[2], Ok("[1, 2]".into()));
+    let (span, _) = eval_err("list = [1]; from i in [0] delete list[i + 1]");
+    assert_eq!(span, "i + 1");
+}
+
+#[test]
+fn delete_from_temporary_source() {
+    let (span, message) = eval_err(r#"h = {a: 1}; from k in keys(h) where k == "a" delete k"#);
+    assert_eq!(span, "keys(h)");
+    assert_eq!(
+        message,
+        "delete source should be a variable, or a field or an index of one"
+    );
+    let (span, _) = eval_err("a = [1]; from x in a join y in [1] on x == y delete y");
+    assert_eq!(span, "[1]");
+}
+
+#[test]
+fn delete_entry_of_non_collection() {
+    let (span, message) = eval_err("h = {a: 1}; from x in [1] delete h.a[0]");
+    assert_eq!(span, "h.a");
+    assert_eq!(message, "expect a 'hash' or a 'list', not 'i64'");
+}
+
+#[test]
+fn delete_items_matched_by_join() {
+    let src = "a = [1, 2, 3]; b = [3, 1, 1]; from x in a join y in b on x == y delete x; a";
+    assert_eq!(eval(src), "[2]");
+    let src = "a = [1, 2, 3]; b = [3]; from x in a left join y in b on x == y where y == null delete x; a";
+    assert_eq!(eval(src), "[3]");
+}
+
+#[test]
+fn delete_returning() {
+    let src = "list = [1, 2, 3]; from x in list where x != 2 delete x returning x * 10";
+    assert_eq!(eval(src), "[10, 30]");
+    let src = "a = [1, 2]; b = [2, 3]; from x in a join y in b on x == y delete y returning [x, y]";
+    assert_eq!(eval(src), "[[2, 2]]");
+    let src = r#"h = {a: 1, b: 2}; from k in ["b"] delete h[k] returning h[k]"#;
+    assert_eq!(eval(src), "[2]");
+}
@@ -0,0 +1,67 @@
+mod util;
+use util::*;
+
+use sigma_runtime::Options;
+
+const LENIENT: Options = Options {
+    strict: false,
+    lenient: true,
+};
+
+#[test]
+fn safe_field() {
+    assert_eq!(eval("x = {a: {b: 1}}; x?.a?.b"), "1");
+    assert_eq!(eval("x = {a: 1}; x?.b"), "null");
+    assert_eq!(eval("x = null; x?.a"), "null");
+}
+
+#[test]
+fn safe_field_skips_rest_of_chain() {
+    assert_eq!(eval("x = null; x?.a.b"), "null");
+    assert_eq!(eval("x = null; x?.a.b[0]"), "null");
+    assert_eq!(eval("x = null; x?.f(1)"), "null");
+    assert_eq!(eval("x = null; x?.a.b ?? 2"), "2");
+}
+
+#[test]
+fn safe_field_of_null_field() {
+    let (span, message) = eval_err("x = {a: null}; x?.a.b");
+    assert_eq!(span, "x?.a.b");
+    assert_eq!(message, "'null' doesn't support field access operation");
+    assert_eq!(eval("x = {a: null}; x?.a?.b"), "null");
+}
+
+#[test]
+fn missing_field() {
+    let (span, message) = eval_err("x = {}; x.a");
+    assert_eq!(span, "x.a");
+    assert_eq!(message, "field 'a' is not found");
+}
+
+#[test]
+fn coalesce() {
+    assert_eq!(eval("null ?? 1"), "1");
+    assert_eq!(eval("0 ?? 1"), "0");
+    assert_eq!(eval("null ?? null ?? 2"), "2");
+    assert_eq!(eval("x = {}; x?.a ?? \"none\""), r#""none""#);
+}
+
+#[test]
+fn coalesce_skips_right_side() {
+    assert_eq!(eval("x = [1]; 1 ?? x[5]"), "1");
+}
+
+#[test]
+fn lenient_field() {
+    assert_eq!(eval_with(LENIENT, "x = {}; x.a.b"), "null");
+    assert_eq!(eval_with(LENIENT, "x = null; x.a"), "null");
+}
+
+#[test]
+fn lenient_compound_assignment() {
+    let (span, message) = eval_err_with(LENIENT, "x = {}; x.missing += 1");
+    assert_eq!(span, "x.missing += 1");
+    assert_eq!(message, "'null' doesn't support + operation");
+    let (_, message) = eval_err("x = {}; x.missing += 1");
+    assert_eq!(message, "field 'missing' is not found");
+}
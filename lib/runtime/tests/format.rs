@@ -0,0 +1,24 @@
+mod util;
+use util::*;
+
+#[test]
+fn format_values() {
+    let src = r#"x = {name: "a", n: 2}; f"{x.name} has {x.n + 1} {[1, "s"]} {null}""#;
+    assert_eq!(eval(src), r#""a has 3 [1, \"s\"] null""#);
+    let src = r#"from x in [{a: "x", b: "y"}] select f"{x.a} <{x.b}>""#;
+    assert_eq!(eval(src), r#"["x <y>"]"#);
+}
+
+#[test]
+fn braces_and_escapes() {
+    assert_eq!(eval(r#"f"{{x}} }}""#), r#""{x} }""#);
+    assert_eq!(eval(r#"f"{ {"k": 1}.k }""#), r#""1""#);
+    assert_eq!(eval(r#"f"a\tb""#), r#""a\tb""#);
+}
+
+#[test]
+fn error_in_embedded_expression() {
+    let (span, message) = eval_err(r#"f"value: {nope}""#);
+    assert_eq!(span, "nope");
+    assert_eq!(message, "name 'nope' is not defined");
+}
@@ -0,0 +1,66 @@
+mod util;
+use util::*;
+
+#[test]
+fn call_lambda() {
+    assert_eq!(eval("add = fn(x, y) => x + y; add(1, 2)"), "3");
+    assert_eq!(eval("n = 10; f = fn([a, b]) => a + b + n; f([1, 2])"), "13");
+}
+
+#[test]
+fn recursion() {
+    let src = "fn fact(n) => if n <= 1 then 1 else n * fact(n - 1); fact(5)";
+    assert_eq!(eval(src), "120");
+    // The function calls itself even if the name is rebound.
+    let src = "fn count(n) => if n == 0 then 0 else 1 + count(n - 1); c = count; count = 1; c(3)";
+    assert_eq!(eval(src), "3");
+}
+
+#[test]
+fn closures_outlive_their_scope() {
+    assert_eq!(eval("make = fn(n) => fn(x) => x + n; make(1)(2)"), "3");
+    let src = "make = fn(n) => fn add(x) => if x > 0 then add(x - 1) + 1 else n; make(10)(2)";
+    assert_eq!(eval(src), "12");
+    let src = "make = fn(n) => { let k = 2; fn add(x) => x + n * k }; make(10)(1)";
+    assert_eq!(eval(src), "21");
+}
+
+#[test]
+fn named_and_anonymous_functions_share_the_scope() {
+    let src = "make = fn() => { let n = 1; fn get() => n; n = 2; get }; make()()";
+    assert_eq!(eval(src), "2");
+    let src = "make = fn() => { let n = 1; get = fn() => n; n = 2; get }; make()()";
+    assert_eq!(eval(src), "2");
+    // A function sees the names declared after it, also after its scope ends.
+    let src = "make = fn() => { fn get() => helper(); fn helper() => 42; get }; make()()";
+    assert_eq!(eval(src), "42");
+}
+
+#[test]
+fn block_body() {
+    let src = "fn norm(x) => {\n  let n = x * x\n  n + 1\n}\nnorm(2)";
+    assert_eq!(eval(src), "5");
+    assert_eq!(eval("f = fn() => {}; f()"), "{}");
+    assert_eq!(eval("f = fn(x) => {a: x}; f(1).a"), "1");
+    assert_eq!(eval("f = fn() => { let a = 1 }; f()"), "null");
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+    let (span, message) = eval_err("f = fn(x) => x; f(1, 2)");
+    assert_eq!(span, "f(1, 2)");
+    assert_eq!(message, "expect 1 arguments, not 2");
+}
+
+#[test]
+fn error_in_body() {
+    let src = "fn f(x) => x.a\ng = fn(y) => f(y)\ng(1)";
+    let (span, message) = eval_err(src);
+    assert_eq!(span, "x.a");
+    assert_eq!(message, "'i64' doesn't support field access operation");
+    let labels = eval_err_labels(src);
+    assert_eq!(
+        labels,
+        [("f", "called here".into()), ("g", "called here".into())]
+    );
+}
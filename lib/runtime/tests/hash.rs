@@ -0,0 +1,30 @@
+mod util;
+use util::*;
+
+#[test]
+fn entries_in_insertion_order() {
+    let src = "h = {d: 1, b: 2, c: 3, a: 4}; from (k, v) in h select k";
+    assert_eq!(eval(src), r#"["d", "b", "c", "a"]"#);
+    assert_eq!(eval("h = {b: 1}; h.a = 2; h.b = 3; h"), "{b: 3, a: 2}");
+    assert_eq!(
+        eval("h = {b: 1, a: 2}; [keys(h), values(h)]"),
+        r#"[["b", "a"], [1, 2]]"#
+    );
+}
+
+#[test]
+fn limit_on_hash_entries() {
+    let src = "h = {c: 1, a: 2, b: 3}; from x in h select x.key limit 2";
+    assert_eq!(eval(src), r#"["c", "a"]"#);
+}
+
+#[test]
+fn delete_keeps_order() {
+    let src = r#"h = {a: 1, b: 2, c: 3, d: 4}; from k in ["b"] delete h[k]; h.e = 5; h"#;
+    assert_eq!(eval(src), "{a: 1, c: 3, d: 4, e: 5}");
+}
+
+#[test]
+fn equality_ignores_order() {
+    assert_eq!(eval("{a: 1, b: 2} == {b: 2, a: 1}"), "true");
+}
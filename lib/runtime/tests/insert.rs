@@ -0,0 +1,29 @@
+mod util;
+use util::*;
+
+#[test]
+fn insert_items() {
+    assert_eq!(eval("list = [1]; into list insert 2, 3"), "2");
+    assert_eq!(eval("list = [1]; into list insert 2, 3; list"), "[1, 2, 3]");
+}
+
+#[test]
+fn returning_binds_a_chosen_name() {
+    let src = "item = 10; list = []; into list insert 1, 2 returning x => x + item";
+    assert_eq!(eval(src), "[11, 12]");
+    let src = "list = []; into list insert {id: 1, n: 2} returning {id} => id";
+    assert_eq!(eval(src), "[1]");
+}
+
+#[test]
+fn returning_before_insert() {
+    let results = exec_all(
+        "list = []
+        into list insert 1, 2, {} returning x => x + 1
+        list",
+    );
+    assert!(results[1].is_err());
+    assert_eq!(results[2], Ok("[]".into()));
+    let src = "list = [0]; into list insert 1 returning x => x in list";
+    assert_eq!(eval(src), "[false]");
+}
@@ -0,0 +1,106 @@
+mod util;
+use util::*;
+
+#[test]
+fn lateral_after_join() {
+    let src = r#"
+        orders = [{id: 1, items: ["a", "b"]}, {id: 2, items: []}, {id: 3, items: ["c"]}]
+        prices = [{name: "a", price: 10}, {name: "c", price: 30}]
+        from o in orders, item in o.items join p in prices on p.name == item select [o.id, p.price]
+    "#;
+    assert_eq!(eval(src), "[[1, 10], [3, 30]]");
+    let src = "from x in [[1], [2]] join y in [1, 2] on y == x[0], z in [y, 10] select [y, z]";
+    assert_eq!(eval(src), "[[1, 1], [1, 10], [2, 2], [2, 10]]");
+}
+
+#[test]
+fn lateral_sources() {
+    let src = r#"
+        groups = [{name: "g", users: [{name: "a", tags: ["x", "y"]}, {name: "b", tags: []}]}]
+        from g in groups, u in g.users, tag in u.tags select f"{g.name}/{u.name}/{tag}"
+    "#;
+    assert_eq!(eval(src), r#"["g/a/x", "g/a/y"]"#);
+    let src = "from {id, items} in [{id: 1, items: [[2, 3]]}], [a, b] in items select id + a + b";
+    assert_eq!(eval(src), "[6]");
+    let src = "from x in [1, 2], y in 0..x where y > 0 select [x, y]";
+    assert_eq!(eval(src), "[[2, 1]]");
+}
+
+#[test]
+fn lateral_source_not_iterable() {
+    let (_, message) = eval_err("from x in [{items: 1}], y in x.items select y");
+    assert_eq!(message, "'i64' is not iterable");
+}
+
+const TABLES: &str = r#"
+    users = [{id: 1, name: "a"}, {id: 2, name: "b"}]
+    orders = [{user: 1, product: 10}, {user: 1, product: 20}]
+    products = [{id: 10, name: "pen"}]
+"#;
+
+#[test]
+fn inner_join() {
+    let src = format!(
+        "{TABLES} from u in users join o in orders on o.user == u.id select [u.name, o.product]"
+    );
+    assert_eq!(eval(&src), r#"[["a", 10], ["a", 20]]"#);
+}
+
+#[test]
+fn chained_left_joins() {
+    let src = format!(
+        "{TABLES} from u in users left join o in orders on o.user == u.id
+            left join p in products on p.id == o.product select [u.name, p?.name]"
+    );
+    assert_eq!(eval(&src), r#"[["a", "pen"], ["a", null], ["b", null]]"#);
+}
+
+#[test]
+fn inner_join_after_left_join() {
+    let src = format!(
+        "{TABLES} from u in users left join o in orders on o.user == u.id
+            join p in products on p.id == o.product select u.name"
+    );
+    assert_eq!(eval(&src), r#"["a"]"#);
+}
+
+#[test]
+fn full_join() {
+    let src = "from x in [1, 2] full join y in [2, 3] on x == y select [x, y]";
+    assert_eq!(eval(src), "[[1, null], [2, 2], [null, 3]]");
+}
+
+#[test]
+fn full_join_then_left_join() {
+    let src = "from x in [1] full join y in [2] on x == y
+        left join z in [{x: 1}] on z.x == x select [x, y, z]";
+    assert_eq!(eval(src), "[[1, null, {x: 1}], [null, 2, null]]");
+}
+
+#[test]
+fn on_clause_with_nested_expressions() {
+    let src = "from x in [1] join z in [5] on z == (if true then 5 else 0) select [x, z]";
+    assert_eq!(eval(src), "[[1, 5]]");
+    let src =
+        "from x in [1] join z in [5] on if true { let y = z; y == 5 } else { false } select [x, z]";
+    assert_eq!(eval(src), "[[1, 5]]");
+    // The name padded with null by the full join is still found in a block.
+    let src = "from x in [1] full join y in [2] on x == y
+        left join z in [{x: null}] on if true { z.x == x } else { false } select [x, y, z]";
+    assert_eq!(eval(src), "[[1, null, null], [null, 2, null]]");
+}
+
+#[test]
+fn hash_join_matches_nested_loop() {
+    // The equality is answered by an index, and the rest of the on clause
+    // is still checked.
+    let src = "from x in [1, 2, 3] join y in [3, 2, 1, 2] on x == y && y > 1 select [x, y]";
+    assert_eq!(eval(src), "[[2, 2], [2, 2], [3, 3]]");
+}
+
+#[test]
+fn hash_join_key_error() {
+    let (span, message) = eval_err("from x in [1] join y in [{a: 1}, 2] on y.a == x");
+    assert_eq!(span, "y.a");
+    assert!(message.contains("'i64'"), "{message}");
+}
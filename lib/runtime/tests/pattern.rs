@@ -0,0 +1,40 @@
+mod util;
+use util::*;
+
+#[test]
+fn hash_and_list_patterns() {
+    let src = r#"users = [{name: "a", email: "a@x"}]; from {name, email} in users select f"{name} <{email}>""#;
+    assert_eq!(eval(src), r#"["a <a@x>"]"#);
+    assert_eq!(
+        eval("from [a, b] in [[1, 2], [3, 4]] select a + b"),
+        "[3, 7]"
+    );
+    let src = "from {a: [x, {y}]} in [{a: [1, {y: 2}]}] select [x, y]";
+    assert_eq!(eval(src), "[[1, 2]]");
+}
+
+#[test]
+fn patterns_in_joins() {
+    let src = "from {id} in [{id: 1}, {id: 2}] join [k, v] in [[2, \"b\"]] on k == id select v";
+    assert_eq!(eval(src), r#"["b"]"#);
+}
+
+#[test]
+fn missing_field() {
+    let (span, message) = eval_err("from {a, b} in [{a: 1}] select a");
+    assert_eq!(span, "b");
+    assert_eq!(message, "field 'b' is not found");
+    let (span, message) = eval_err("from {a} in [1] select a");
+    assert_eq!(span, "{a}");
+    assert_eq!(message, "expect a 'hash', not 'i64'");
+}
+
+#[test]
+fn wrong_list_length() {
+    let (span, message) = eval_err("from [a, b] in [[1]] select a");
+    assert_eq!(span, "[a, b]");
+    assert_eq!(message, "expect 2 items, not 1");
+    let (span, message) = eval_err("from x in [[1]], [a] in [1] select a");
+    assert_eq!(span, "[a]");
+    assert_eq!(message, "expect a 'list', not 'i64'");
+}
@@ -0,0 +1,42 @@
+mod util;
+use util::*;
+
+#[test]
+fn iterate_ranges() {
+    assert_eq!(eval("from i in 0..3 select i"), "[0, 1, 2]");
+    assert_eq!(eval("from i in 1..=3 select i"), "[1, 2, 3]");
+    assert_eq!(eval("from i in 3..1 select i"), "[]");
+    assert_eq!(eval("1..4"), "1..4");
+}
+
+#[test]
+fn contains() {
+    assert_eq!(
+        eval("[3 in 1..=3, 3 in 1..3, 0 in 0..0, 10 in 5..]"),
+        "[true, false, false, true]"
+    );
+}
+
+#[test]
+fn slices() {
+    assert_eq!(eval("[1, 2, 3, 4][1..3]"), "[2, 3]");
+    assert_eq!(eval("[1, 2, 3, 4][-2..]"), "[3, 4]");
+    assert_eq!(eval("[1, 2][..]"), "[1, 2]");
+    assert_eq!(eval(r#""hello"[..=1]"#), r#""he""#);
+    // Strings are sliced by characters.
+    assert_eq!(eval(r#""héllo"[1..3]"#), r#""él""#);
+}
+
+#[test]
+fn errors() {
+    let (_, message) = eval_err("from i in ..3 select i");
+    assert_eq!(message, "range '..3' without a start is not iterable");
+    let (span, message) = eval_err("[1, 2, 3][0..10]");
+    assert_eq!(span, "[1, 2, 3][0..10]");
+    assert_eq!(message, "range '0..10' out of bounds");
+    let (_, message) = eval_err("[1, 2, 3][2..1]");
+    assert_eq!(message, "range '2..1' out of bounds");
+    let (span, message) = eval_err("1.5..2");
+    assert_eq!(span, "1.5");
+    assert_eq!(message, "range bound must be 'i64', not 'f64'");
+}
@@ -0,0 +1,47 @@
+mod util;
+use util::*;
+
+use sigma_runtime::Options;
+
+const STRICT: Options = Options {
+    strict: true,
+    lenient: false,
+};
+
+#[test]
+fn let_declares_in_current_scope() {
+    assert_eq!(eval("x = 1; { let x = 2; x = 3 }; x"), "1");
+    assert_eq!(eval("let {a, b: [c]} = {a: 1, b: [2]}; a + c"), "3");
+    let (span, message) = eval_err("{ let y = 1 }; y");
+    assert_eq!(span, "y");
+    assert_eq!(message, "name 'y' is not defined");
+}
+
+#[test]
+fn assignment_updates_owning_scope() {
+    assert_eq!(eval("x = 1; { x = 2 }; x"), "2");
+    let src = "total = 0; from x in [{n: 1}, {n: 2}] update total += x.n; total";
+    assert_eq!(eval(src), "3");
+    let src = "total = 0; from x in [1, 2] select (total += x); total";
+    assert_eq!(eval(src), "3");
+    // Without a scope that has it, a name is declared in the current scope.
+    let (_, message) = eval_err("{ z = 1 }; z");
+    assert_eq!(message, "name 'z' is not defined");
+}
+
+#[test]
+fn strict_mode() {
+    let (span, message) = eval_err_with(STRICT, "x = 1");
+    assert_eq!(span, "x = 1");
+    assert_eq!(message, "name 'x' is not declared");
+    let src = "let total = 0; from x in [1, 2] update total += x; total";
+    assert_eq!(eval_with(STRICT, src), "3");
+    let (span, message) = eval_err_with(STRICT, "from x in [1, 2] update total += x");
+    assert_eq!(span, "total");
+    assert_eq!(message, "name 'total' is not defined");
+    // Names bound by patterns and named functions are declared.
+    assert_eq!(
+        eval_with(STRICT, "fn f(x) => x + 1; from y in [1] select f(y)"),
+        "[2]"
+    );
+}
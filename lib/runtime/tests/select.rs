@@ -0,0 +1,121 @@
+mod util;
+use util::*;
+
+#[test]
+fn order_by_keys() {
+    let src = r#"
+        people = [{name: "b", age: 2}, {name: "a", age: 2}, {name: "c", age: 1}]
+        from x in people order by x.age desc, x.name select x.name
+    "#;
+    assert_eq!(eval(src), r#"["a", "b", "c"]"#);
+}
+
+#[test]
+fn order_by_incomparable_keys() {
+    let (span, message) = eval_err(r#"from x in [1, "a", 2] order by x"#);
+    assert_eq!(span, "x");
+    assert_eq!(message, "'str' cannot be compared with 'i64'");
+}
+
+#[test]
+fn order_by_nan() {
+    let (span, message) = eval_err("from x in [1.0, 0.0 / 0.0] order by x * 2.0");
+    assert_eq!(span, "x * 2.0");
+    assert_eq!(message, "'f64' cannot be compared with 'f64'");
+}
+
+#[test]
+fn compare_nan() {
+    assert_eq!(eval("nan = 0.0 / 0.0; nan == nan"), "false");
+    let (span, _) = eval_err("0.0 / 0.0 < 1.0");
+    assert_eq!(span, "0.0 / 0.0 < 1.0");
+}
+
+#[test]
+fn group_by_key_hides_outer_name() {
+    let src =
+        "key = 5; groups = from x in [1, 2, 2] group by x select [key, count()]; [groups, key]";
+    assert_eq!(eval(src), "[[[1, 1], [2, 2]], 5]");
+}
+
+#[test]
+fn group_by_variable_hides_aggregate() {
+    let src = "count = 0; from x in [1, 2, 2] group by x select count";
+    assert_eq!(eval(src), "[0, 0]");
+}
+
+#[test]
+fn group_by_without_select() {
+    let src = "from g in (from x in 1..=6 group by x % 2) select [g.key, g.items]";
+    assert_eq!(eval(src), "[[1, [1, 3, 5]], [0, [2, 4, 6]]]");
+    let (span, message) = eval_err("from x in 1..=6 group by x % 2 select items");
+    assert_eq!(span, "items");
+    assert_eq!(message, "name 'items' is not defined");
+}
+
+#[test]
+fn aggregate_in_nested_scopes_of_group() {
+    let src = "from x in [1, 2, 2] group by x select if true { count() } else { 0 }";
+    assert_eq!(eval(src), "[1, 2]");
+    let src = "from x in [1, 2, 2] group by x select (from y in [10] select y + sum(x))";
+    assert_eq!(eval(src), "[[11], [14]]");
+}
+
+#[test]
+fn aggregate_outside_group() {
+    assert_eq!(eval("count([1, 2])"), "2");
+    let (span, message) = eval_err("from x in [1, 2] select count()");
+    assert_eq!(span, "count()");
+    assert_eq!(
+        message,
+        "aggregate function called outside of a group expects 1 argument"
+    );
+}
+
+#[test]
+fn limit_and_offset() {
+    let src = "from x in [1, 2, 3, 4, 5] where x > 1 select x limit 2 offset 1";
+    assert_eq!(eval(src), "[3, 4]");
+    assert_eq!(eval("from x in [1, 2] select x limit 0"), "[]");
+    assert_eq!(eval("from x in [1, 2] select x offset 5"), "[]");
+    let src = "from x in [3, 1, 2] order by x select x limit 2";
+    assert_eq!(eval(src), "[1, 2]");
+    let src = "from x in [1, 2, 1, 3] select distinct x limit 2 offset 1";
+    assert_eq!(eval(src), "[2, 3]");
+}
+
+#[test]
+fn limit_stops_early() {
+    // An open-ended range never ends, so only an early stop returns.
+    let src = "from i in 0.. where i % 2 == 0 select i * 10 limit 3 offset 1";
+    assert_eq!(eval(src), "[20, 40, 60]");
+    let src = "n = 0; from i in 0.. select (n += 1) limit 2; n";
+    assert_eq!(eval(src), "2");
+}
+
+#[test]
+fn invalid_limit() {
+    let (span, message) = eval_err("from x in [1] select x limit -1");
+    assert_eq!(span, "-1");
+    assert_eq!(message, "expect a non-negative 'i64', not '-1'");
+    let (span, _) = eval_err(r#"from x in [1] select x offset "a""#);
+    assert_eq!(span, r#""a""#);
+}
+
+#[test]
+fn select_distinct() {
+    let src = "from x in [1, 2, 1, 3, 2] select distinct x";
+    assert_eq!(eval(src), "[1, 2, 3]");
+    let src = "from x in [[1], [1], [2]] select distinct x[0] * 2";
+    assert_eq!(eval(src), "[2, 4]");
+    // Without a projection, the items themselves are compared by value.
+    let src = "from x in [{a: 1}, {a: 1}, {b: 1, a: 2}, {a: 2, b: 1}] select distinct";
+    assert_eq!(eval(src), "[{a: 1}, {b: 1, a: 2}]");
+}
+
+#[test]
+fn select_distinct_unhashable() {
+    let (span, message) = eval_err("from x in [fn() => 1] select distinct x");
+    assert_eq!(span, "from x in [fn() => 1] select distinct x");
+    assert_eq!(message, "'func' is not hashable");
+}
@@ -0,0 +1,31 @@
+mod util;
+use util::*;
+
+#[test]
+fn union() {
+    let src = "from x in [1, 2, 2, 3] union from y in [2, 3, 4]";
+    assert_eq!(eval(src), "[1, 2, 3, 4]");
+}
+
+#[test]
+fn intersect_and_except() {
+    let src = "from x in [1, 2, 3, 3] intersect from y in [3, 2, 5]";
+    assert_eq!(eval(src), "[2, 3]");
+    let src = "from x in [1, 2, 3, 1] except from y in [2]";
+    assert_eq!(eval(src), "[1, 3]");
+}
+
+#[test]
+fn chained() {
+    let src = "from x in [1, 2] union from y in [3] except from z in [1] select z";
+    assert_eq!(eval(src), "[2, 3]");
+}
+
+#[test]
+fn mutating_operand() {
+    let src = "b = [1, 2]; from x in [3] union from y in b where y == 1 delete y";
+    let (span, _) = eval_err(src);
+    assert_eq!(span, "from y in b where y == 1 delete y");
+    let src = "b = [1, 2]; (from x in [3] union from y in b where y == 1 delete y); b";
+    assert!(eval_err(src).1.contains("set operation"));
+}
@@ -0,0 +1,62 @@
+mod util;
+use util::*;
+
+#[test]
+fn block_statement() {
+    assert_eq!(eval("{ a = 1; a + 1 }"), "2");
+    assert_eq!(eval("a = 1; { let a = 2 }; a"), "1");
+    assert_eq!(eval("a = 1; { a = 2 }; a"), "2");
+    let (span, message) = eval_err("{ let b = 1 }; b");
+    assert_eq!(span, "b");
+    assert_eq!(message, "name 'b' is not defined");
+}
+
+#[test]
+fn hash_statement() {
+    assert_eq!(eval("{a: 1}"), "{a: 1}");
+    assert_eq!(eval("{}"), "{}");
+}
+
+#[test]
+fn break_and_continue() {
+    let src = "
+        odds = []
+        for x in 1.. {
+            if x > 5 { break }
+            if x % 2 == 0 { continue }
+            into odds insert x
+        }
+        odds
+    ";
+    assert_eq!(eval(src), "[1, 3, 5]");
+    let src = "
+        n = 0
+        while true {
+            n += 1
+            { if n == 3 { break } }
+        }
+        n
+    ";
+    assert_eq!(eval(src), "3");
+}
+
+#[test]
+fn break_stops_innermost_loop() {
+    let src = "
+        pairs = []
+        for x in [1, 2] {
+            for y in [1, 2, 3] {
+                if y > x { break }
+                into pairs insert [x, y]
+            }
+        }
+        pairs
+    ";
+    assert_eq!(eval(src), "[[1, 1], [2, 1], [2, 2]]");
+}
+
+#[test]
+fn negation_on_next_line() {
+    assert_eq!(eval("a = 1\n-1\na"), "1");
+    assert_eq!(eval("a = 1 -\n1\na"), "0");
+}
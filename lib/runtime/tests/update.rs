@@ -0,0 +1,163 @@
+mod util;
+use util::*;
+
+#[test]
+fn update_items() {
+    let src = "list = [{a: 1}, {a: 2}]; from x in list where x.a > 1 update x.a *= 10; list";
+    assert_eq!(eval(src), "[{a: 1}, {a: 20}]");
+}
+
+#[test]
+fn update_keeps_aliases() {
+    let src = "a = {n: 1}; list = [a]; from x in list update x.n = 2; [a, list]";
+    assert_eq!(eval(src), "[{n: 2}, [{n: 2}]]");
+    let src = "list = [{n: 1}]; a = list[0]; from x in list update x = {n: 3}; [a, list]";
+    assert_eq!(eval(src), "[{n: 1}, [{n: 3}]]");
+}
+
+#[test]
+fn failed_update_restores_items_in_place() {
+    let results = exec_all(
+        r#"
+        inner = {m: 1}
+        list = [{a: 1, inner: inner}, {a: "s", inner: inner}]
+        first = list[0]
+        from x in list update x.inner.m += 1, x.a += 1
+        [list[0].a, list[1].a, first.a, inner.m]
+        first == list[0] && inner == list[1].inner
+        first.a = 5; list[0].a
+        inner.m = 7; list[1].inner.m
+        "#,
+    );
+    assert_eq!(results[3], Err("'str' doesn't support + operation".into()));
+    assert_eq!(results[4], Ok(r#"[1, "s", 1, 1]"#.into()));
+    assert_eq!(results[5], Ok("true".into()));
+    assert_eq!(results[7], Ok("5".into()));
+    assert_eq!(results[9], Ok("7".into()));
+}
+
+#[test]
+fn failed_update_restores_outer_variables() {
+    let results = exec_all(
+        r#"
+        list = [1, 2, "s"]
+        n = 0
+        seen = []
+        from x in list update n += 1, x = x + 1, into seen insert x
+        [list, n, seen]
+        "#,
+    );
+    assert_eq!(results[3], Err("'str' doesn't support + operation".into()));
+    assert_eq!(results[4], Ok(r#"[[1, 2, "s"], 0, []]"#.into()));
+}
+
+#[test]
+fn failed_delete_restores_outer_variables() {
+    let results = exec_all(
+        r#"
+        list = [0, 0, 5]
+        n = 0
+        from x in list delete x returning n += [1][x]
+        [list, n]
+        "#,
+    );
+    assert!(results[2].is_err());
+    assert_eq!(results[3], Ok("[[0, 0, 5], 0]".into()));
+}
+
+#[test]
+fn failed_update_undoes_nested_writes() {
+    let results = exec_all(
+        r#"
+        list = [1, "s"]
+        h = {a: 1, b: 2, c: 3}
+        other = [{n: 1}, {n: 2}]
+        from x in list update total = x + 1, (from k in ["b"] delete h[k]), (from y in other update y.n = 0)
+        [list, h, other]
+        total
+        "#,
+    );
+    assert_eq!(results[3], Err("'str' doesn't support + operation".into()));
+    assert_eq!(
+        results[4],
+        Ok(r#"[[1, "s"], {a: 1, b: 2, c: 3}, [{n: 1}, {n: 2}]]"#.into())
+    );
+    assert_eq!(results[5], Err("name 'total' is not defined".into()));
+}
+
+#[test]
+fn update_hash_values() {
+    let src = r#"h = {a: 1, b: 2}; from (k, v) in h where k == "a" update v += 10; h.a + h.b"#;
+    assert_eq!(eval(src), "13");
+    let src = "h = {a: 1}; from x in h update x.value = [x.key]; h";
+    assert_eq!(eval(src), r#"{a: ["a"]}"#);
+}
+
+#[test]
+fn update_hash_keys() {
+    let results = exec_all(
+        r#"
+        h = {a: 1, b: 2}
+        from (k, v) in h update v *= 10, k = if k == "b" then 5 else k
+        [h.a, h.b]
+        "#,
+    );
+    assert!(results[1]
+        .as_ref()
+        .is_err_and(|e| e.starts_with("expect an entry with key 'b'")));
+    assert_eq!(results[2], Ok("[1, 2]".into()));
+    let (span, _) = eval_err("h = {a: 1}; from x in h update x = 1");
+    assert_eq!(span, "x");
+}
+
+#[test]
+fn update_from_join() {
+    let results = exec_all(
+        r#"
+        items = [{id: 1, price: 0}, {id: 2, price: 0}, {id: 3, price: 0}]
+        prices = [{id: 1, price: 10}, {id: 3, price: 30}, {id: 3, price: 31}]
+        from x in items join p in prices on p.id == x.id update x.price = p.price
+        items
+        from x in items left join p in prices on p.id == x.id where p == null update x.price = -1
+        items
+        "#,
+    );
+    // An item is updated once, with its first matching row.
+    assert_eq!(results[2], Ok("2".into()));
+    assert_eq!(
+        results[3],
+        Ok("[{id: 1, price: 10}, {id: 2, price: 0}, {id: 3, price: 30}]".into())
+    );
+    assert_eq!(results[4], Ok("1".into()));
+    assert_eq!(
+        results[5],
+        Ok("[{id: 1, price: 10}, {id: 2, price: -1}, {id: 3, price: 30}]".into())
+    );
+}
+
+#[test]
+fn unsupported_joins_in_update_and_delete() {
+    let (span, message) =
+        eval_err("a = [1]; from x in a full join y in [1] on x == y update x = 2");
+    assert_eq!(span, "full join y in [1] on x == y");
+    assert_eq!(message, "full join is not supported in update expressions");
+    let (span, message) = eval_err("a = [[1]]; from x in a, y in x update x = 2");
+    assert_eq!(span, "y in x");
+    assert_eq!(
+        message,
+        "lateral source is not supported in update expressions"
+    );
+    let (span, message) = eval_err("a = [1]; from x in a full join y in [1] on x == y delete x");
+    assert_eq!(span, "full join y in [1] on x == y");
+    assert_eq!(message, "full join is not supported in delete expressions");
+}
+
+#[test]
+fn update_returning() {
+    let src = "list = [{a: 1}, {a: 2}]; from x in list where x.a > 1 update x.a += 1 returning x";
+    assert_eq!(eval(src), "[{a: 3}]");
+    // The list is only written after every row, so it's unchanged in the
+    // returning clause.
+    let src = "list = [1, 2]; from x in list update x *= 10 returning [x, list[0]]";
+    assert_eq!(eval(src), "[[10, 1], [20, 1]]");
+}
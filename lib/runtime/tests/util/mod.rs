@@ -0,0 +1,72 @@
+#![allow(dead_code)]
+
+use sigma_parser::Parser;
+use sigma_runtime::{Options, Runtime};
+
+/// Runs all statements and returns the value of the last one, formatted.
+pub fn eval(src: &str) -> String {
+    eval_with(Options::default(), src)
+}
+
+pub fn eval_with(options: Options, src: &str) -> String {
+    match run(options, src) {
+        Ok(value) => value,
+        Err((span, message)) => panic!("{message} at {:?}", &src[span]),
+    }
+}
+
+/// Runs all statements and returns the source at the span of the error, and
+/// its message.
+pub fn eval_err(src: &str) -> (&str, String) {
+    eval_err_with(Options::default(), src)
+}
+
+pub fn eval_err_with(options: Options, src: &str) -> (&str, String) {
+    match run(options, src) {
+        Ok(value) => panic!("expect an error, not {value}"),
+        Err((span, message)) => (&src[span], message),
+    }
+}
+
+/// Runs all statements and returns the source at the spans of the secondary
+/// labels of the error, and their messages.
+pub fn eval_err_labels(src: &str) -> Vec<(&str, String)> {
+    let rt = Runtime::new(Options::default());
+    for stmt in Parser::new(src) {
+        let stmt = stmt.unwrap_or_else(|e| panic!("{} at {:?}", e.message, &src[e.span]));
+        if let Err(e) = rt.exec(&stmt) {
+            return e
+                .labels
+                .into_iter()
+                .map(|label| (&src[label.span], label.message))
+                .collect();
+        }
+    }
+    panic!("expect an error")
+}
+
+/// Runs all statements like a shell does, continuing after runtime errors,
+/// and returns the value or the error message of each statement.
+pub fn exec_all(src: &str) -> Vec<Result<String, String>> {
+    let rt = Runtime::new(Options::default());
+    Parser::new(src)
+        .map(|stmt| {
+            let stmt = stmt.unwrap_or_else(|e| panic!("{} at {:?}", e.message, &src[e.span]));
+            rt.exec(&stmt)
+                .map(|value| value.map(|value| value.to_string()).unwrap_or_default())
+                .map_err(|e| e.message)
+        })
+        .collect()
+}
+
+fn run(options: Options, src: &str) -> Result<String, (std::ops::Range<usize>, String)> {
+    let rt = Runtime::new(options);
+    let mut last = None;
+    for stmt in Parser::new(src) {
+        let stmt = stmt.map_err(|e| (e.span, e.message))?;
+        if let Some(value) = rt.exec(&stmt).map_err(|e| (e.span, e.message))? {
+            last = Some(value);
+        }
+    }
+    Ok(last.map(|value| value.to_string()).unwrap_or_default())
+}
@@ -1,8 +1,55 @@
-use anyhow::Result;
+use std::io::{IsTerminal, Read};
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+use anyhow::{bail, Context, Result};
+use sigma_runtime::Options;
+
+mod report;
+
+mod script;
+use script::Script;
 
 mod shell;
 use shell::Shell;
 
-fn main() -> Result<()> {
-    Shell::default().run()
+const USAGE: &str = "usage: sigma [--strict] [--lenient] [FILE | -e EXPR | -]";
+
+fn main() -> Result<ExitCode> {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let mut options = Options::default();
+    while let Some(arg) = args.first() {
+        match arg.as_str() {
+            "--strict" => options.strict = true,
+            "--lenient" => options.lenient = true,
+            _ => break,
+        }
+        args.remove(0);
+    }
+    let (file, src) = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] if io::stdin().is_terminal() => {
+            return Shell::new(options).run().map(|_| ExitCode::SUCCESS)
+        }
+        [] | ["-"] => ("<stdin>".to_owned(), read_stdin()?),
+        ["-e", expr] => ("<expr>".to_owned(), expr.to_owned()),
+        [path] if !path.starts_with('-') => {
+            let src =
+                fs::read_to_string(path).with_context(|| format!("failed to read '{path}'"))?;
+            (path.to_owned(), src)
+        }
+        _ => bail!(USAGE),
+    };
+    if Script::new(&file, &src, options).run() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+fn read_stdin() -> Result<String> {
+    let mut src = String::new();
+    io::stdin()
+        .read_to_string(&mut src)
+        .context("failed to read stdin")?;
+    Ok(src)
 }
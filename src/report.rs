@@ -0,0 +1,90 @@
+use std::rc::Rc;
+
+use ariadne::{Color, Label, Report, ReportKind};
+use sigma_parser::Span;
+
+pub fn print_error(file: &str, src: &str, span: Span, message: impl ToString) {
+    let mut sources = Sources::new(file, src);
+    let primary = sources.label(span, None, message, Color::Red);
+    print_report(sources, primary, Vec::new());
+}
+
+/// Prints a runtime error, whose spans may refer to the inputs that functions
+/// are defined in.
+pub fn print_runtime_error(file: &str, src: &str, err: &sigma_runtime::Error) {
+    let mut sources = Sources::new(file, src);
+    let primary = sources.label(
+        err.span.clone(),
+        err.source.as_ref(),
+        &err.message,
+        Color::Red,
+    );
+    let labels = err
+        .labels
+        .iter()
+        .map(|label| {
+            sources.label(
+                label.span.clone(),
+                label.source.as_ref(),
+                &label.message,
+                Color::Blue,
+            )
+        })
+        .collect();
+    print_report(sources, primary, labels);
+}
+
+type Id = (String, Span);
+
+fn print_report(sources: Sources, primary: Label<Id>, labels: Vec<Label<Id>>) {
+    let file = sources.inputs[0].0.clone();
+    Report::build(ReportKind::Error, file, 0)
+        .with_label(primary)
+        .with_labels(labels)
+        .finish()
+        .eprint(ariadne::sources(sources.inputs))
+        .unwrap();
+}
+
+// The inputs that labels refer to, by name.
+struct Sources {
+    inputs: Vec<(String, String)>,
+}
+
+impl Sources {
+    fn new(file: &str, src: &str) -> Self {
+        Self {
+            inputs: vec![(file.to_owned(), src.to_owned())],
+        }
+    }
+
+    fn label(
+        &mut self,
+        span: Span,
+        source: Option<&Rc<str>>,
+        message: impl ToString,
+        color: Color,
+    ) -> Label<Id> {
+        let i = match source {
+            None => 0,
+            Some(source) => match self.inputs.iter().position(|(_, src)| **src == **source) {
+                Some(i) => i,
+                None => {
+                    let name = match self.inputs.len() {
+                        1 => "<earlier input>".to_owned(),
+                        n => format!("<earlier input {n}>"),
+                    };
+                    self.inputs.push((name, source.to_string()));
+                    self.inputs.len() - 1
+                }
+            },
+        };
+        let (name, src) = &self.inputs[i];
+        // Spans are byte offsets, but ariadne expects char offsets.
+        let offset = |i: usize| src[..i].chars().count();
+        let span = offset(span.start)..offset(span.end);
+        Label::new((name.clone(), span))
+            .with_color(color)
+            .with_message(message)
+    }
+}
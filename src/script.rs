@@ -0,0 +1,47 @@
+use sigma_parser::Parser;
+use sigma_runtime::{Options, Runtime};
+
+use crate::report::{print_error, print_runtime_error};
+
+pub struct Script<'a> {
+    file: &'a str,
+    src: &'a str,
+    rt: Runtime,
+}
+
+impl<'a> Script<'a> {
+    pub fn new(file: &'a str, src: &'a str, options: Options) -> Self {
+        Self {
+            file,
+            src,
+            rt: Runtime::new(options),
+        }
+    }
+
+    /// Runs all statements and prints the value of the last one.
+    ///
+    /// Returns false if any statement fails to parse or execute.
+    pub fn run(self) -> bool {
+        let mut last = None;
+        for stmt in Parser::new(self.src) {
+            let stmt = match stmt {
+                Ok(stmt) => stmt,
+                Err(err) => {
+                    print_error(self.file, self.src, err.span, err.message);
+                    return false;
+                }
+            };
+            match self.rt.exec(&stmt) {
+                Ok(x) => last = x,
+                Err(err) => {
+                    print_runtime_error(self.file, self.src, &err);
+                    return false;
+                }
+            }
+        }
+        if let Some(x) = last {
+            println!("{x}");
+        }
+        true
+    }
+}
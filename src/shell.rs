@@ -1,59 +1,61 @@
 use anyhow::Result;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
-use sigma_parser::{Parser, Span};
-use sigma_runtime::Runtime;
+use sigma_parser::{ErrorKind, Parser};
+use sigma_runtime::{Options, Runtime};
+
+use crate::report::{print_error, print_runtime_error};
 
-#[derive(Default)]
 pub struct Shell {
     rt: Runtime,
 }
 
 impl Shell {
+    pub fn new(options: Options) -> Self {
+        Self {
+            rt: Runtime::new(options),
+        }
+    }
+
     pub fn run(self) -> Result<()> {
         println!("Sigma {}", env!("CARGO_PKG_VERSION"));
         let mut rl = DefaultEditor::new()?;
+        let mut input = String::new();
         loop {
-            match rl.readline(">>> ") {
+            let prompt = if input.is_empty() { ">>> " } else { "... " };
+            match rl.readline(prompt) {
                 Ok(line) => {
-                    let _ = rl.add_history_entry(&line);
-                    self.exec(&line);
+                    input.push_str(&line);
+                    input.push('\n');
+                    if self.exec(&input) {
+                        let _ = rl.add_history_entry(input.trim_end());
+                        input.clear();
+                    }
                 }
-                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Interrupted) => input.clear(),
                 Err(ReadlineError::Eof) => return Ok(()),
                 Err(err) => return Err(err.into()),
             }
         }
     }
 
-    fn exec(&self, line: &str) {
-        let parser = Parser::new(line);
-        for stmt in parser {
-            match stmt {
-                Ok(stmt) => match self.rt.exec(&stmt) {
-                    Ok(Some(x)) => println!("{x}"),
-                    Ok(_) => {}
-                    Err(err) => print_error(line, err.span, err.message),
-                },
-                Err(err) => {
-                    print_error(line, err.span, err.message);
-                    break;
-                }
+    // Returns false if the input is incomplete and more lines are expected.
+    fn exec(&self, input: &str) -> bool {
+        let stmts = match Parser::new(input).collect::<sigma_parser::Result<Vec<_>>>() {
+            Ok(stmts) => stmts,
+            Err(err) if err.kind == ErrorKind::Incomplete => return false,
+            Err(err) => {
+                print_error("<stdin>", input, err.span, err.message);
+                return true;
+            }
+        };
+        for stmt in stmts {
+            match self.rt.exec(&stmt) {
+                Ok(Some(x)) => println!("{x}"),
+                Ok(_) => {}
+                Err(err) => print_runtime_error("<stdin>", input, &err),
             }
         }
+        true
     }
 }
-
-fn print_error(src: &str, span: Span, message: impl ToString) {
-    use ariadne::{Color, Label, Report, ReportKind, Source};
-    let file = "<stdin>";
-    Report::build(ReportKind::Error, file, 0)
-        .with_label(
-            Label::new((file, span))
-                .with_color(Color::Red)
-                .with_message(message),
-        )
-        .finish()
-        .eprint((file, Source::from(src)))
-        .unwrap();
-}
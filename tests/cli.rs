@@ -0,0 +1,88 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::{env, fs};
+
+// Runs the binary with the arguments and the input, and returns whether it
+// succeeded, and its stdout and stderr.
+fn sigma(args: &[&str], input: &str) -> (bool, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sigma"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = child.wait_with_output().unwrap();
+    (
+        status.success(),
+        String::from_utf8(stdout).unwrap(),
+        String::from_utf8(stderr).unwrap(),
+    )
+}
+
+#[test]
+fn inline_expression() {
+    let (ok, stdout, _) = sigma(&["-e", "a = 1; a + 2"], "");
+    assert!(ok);
+    assert_eq!(stdout, "3\n");
+}
+
+#[test]
+fn script_file() {
+    let path = env::temp_dir().join(format!("sigma-cli-{}.sg", std::process::id()));
+    fs::write(
+        &path,
+        "list = [1, 2, 3]\nfrom x in list where x > 1 select x * 10\n",
+    )
+    .unwrap();
+    let (ok, stdout, _) = sigma(&[path.to_str().unwrap()], "");
+    fs::remove_file(&path).unwrap();
+    assert!(ok);
+    assert_eq!(stdout, "[20, 30]\n");
+}
+
+#[test]
+fn script_from_stdin() {
+    for args in [&[][..], &["-"]] {
+        let (ok, stdout, _) = sigma(args, "x = 2\nx * x\n");
+        assert!(ok, "{args:?}");
+        assert_eq!(stdout, "4\n", "{args:?}");
+    }
+}
+
+#[test]
+fn errors_exit_with_failure() {
+    let (ok, stdout, stderr) = sigma(&["-e", "1 +"], "");
+    assert!(!ok);
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("Error"), "{stderr}");
+    let (ok, _, stderr) = sigma(&["-e", "nope"], "");
+    assert!(!ok);
+    assert!(stderr.contains("name 'nope' is not defined"), "{stderr}");
+    let (ok, _, stderr) = sigma(&["/nonexistent/script.sg"], "");
+    assert!(!ok);
+    assert!(stderr.contains("failed to read"), "{stderr}");
+}
+
+#[test]
+fn options() {
+    let (ok, _, stderr) = sigma(&["--strict", "-e", "x = 1"], "");
+    assert!(!ok);
+    assert!(stderr.contains("name 'x' is not declared"), "{stderr}");
+    let (ok, stdout, _) = sigma(&["--lenient", "-e", "null.a"], "");
+    assert!(ok);
+    assert_eq!(stdout, "null\n");
+    let (ok, _, stderr) = sigma(&["--unknown"], "");
+    assert!(!ok);
+    assert!(stderr.contains("usage: sigma"), "{stderr}");
+}